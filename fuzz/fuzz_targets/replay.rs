@@ -0,0 +1,20 @@
+#![no_main]
+
+use std::cell::RefCell;
+
+use libfuzzer_sys::fuzz_target;
+use xtarda2::fuzz;
+use xtarda2::game::Game;
+
+thread_local! {
+    // Loading the fonts, levels and themes takes far longer than a replay
+    static GAME: RefCell<Game> = RefCell::new(Game::new(1920, 1280, "res".to_string()));
+}
+
+fuzz_target!(|data: &[u8]| {
+    GAME.with(|game| {
+        if let Err(e) = fuzz::replay(&mut game.borrow_mut(), data) {
+            panic!("{}", e);
+        }
+    });
+});
@@ -0,0 +1,302 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::mpsc::Sender;
+
+use serde::{Deserialize, Serialize};
+
+use crate::events::{Event, EventKind, Subscriber};
+use crate::profile::Profiles;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Cause;
+
+    fn complete(level: u32) -> EventKind {
+        EventKind::LevelComplete {
+            level,
+            carry_over_pods: true,
+        }
+    }
+
+    fn hear(achievements: &mut Achievements, kind: EventKind) {
+        achievements.notify(&Event {
+            tick: 0,
+            x: 0.0,
+            y: 0.0,
+            kind,
+        });
+    }
+
+    #[test]
+    fn test_unlocking() {
+        let mut achievements = Achievements::default();
+        hear(
+            &mut achievements,
+            EventKind::GameStarted {
+                level: 1,
+                on_record: true,
+            },
+        );
+        for _ in 0..4 {
+            hear(&mut achievements, EventKind::Docked { autodock: false });
+        }
+        // Autodock starts the count again
+        hear(&mut achievements, EventKind::Docked { autodock: true });
+        for _ in 0..4 {
+            hear(&mut achievements, EventKind::Docked { autodock: false });
+        }
+        assert!(!achievements.has(Achievement::SteadyHands));
+        hear(&mut achievements, EventKind::Docked { autodock: false });
+        assert!(achievements.has(Achievement::SteadyHands));
+        assert!(achievements.just_unlocked == [Achievement::SteadyHands]);
+        achievements.just_unlocked.clear();
+
+        hear(&mut achievements, EventKind::PodDropped);
+        for _ in 0..20 {
+            hear(&mut achievements, EventKind::NearMiss { closeness: 0.1 });
+        }
+        hear(
+            &mut achievements,
+            EventKind::PodDestroyed {
+                cause: Cause::Asteroid,
+                ascending: false,
+                from_mothership: 0.5,
+            },
+        );
+        hear(&mut achievements, complete(1));
+        // Neither scraping through nor finishing cleanly
+        assert!(achievements.just_unlocked.is_empty());
+        hear(&mut achievements, EventKind::PodDropped);
+        hear(&mut achievements, EventKind::Landed);
+        hear(&mut achievements, complete(9));
+        assert!(
+            achievements.just_unlocked
+                == [
+                    Achievement::Untouched,
+                    Achievement::FullHangar,
+                    Achievement::Veteran
+                ]
+        );
+        // Each only unlocks once, however far the player gets
+        achievements.just_unlocked.clear();
+        hear(&mut achievements, complete(u32::MAX));
+        assert!(achievements.just_unlocked.is_empty());
+        assert!(achievements.count() == 4);
+
+        // Nothing counts from games that aren't on the record
+        let mut achievements = Achievements::default();
+        hear(
+            &mut achievements,
+            EventKind::GameStarted {
+                level: 10,
+                on_record: false,
+            },
+        );
+        hear(&mut achievements, complete(10));
+        assert!(achievements.count() == 0);
+        // Starting a game further on isn't reaching it
+        hear(
+            &mut achievements,
+            EventKind::GameStarted {
+                level: 12,
+                on_record: true,
+            },
+        );
+        assert!(!achievements.has(Achievement::Veteran));
+        // Finishing cleanly on a level that doesn't carry pods over isn't
+        // enough for a full hangar
+        hear(
+            &mut achievements,
+            EventKind::LevelComplete {
+                level: 3,
+                carry_over_pods: false,
+            },
+        );
+        assert!(achievements.just_unlocked == [Achievement::Untouched]);
+    }
+}
+
+/// Something to aim for, which stays unlocked once done
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Achievement {
+    /// Terrans rescued in a row without autodock
+    SteadyHands,
+    /// A level finished without scraping past an asteroid
+    Untouched,
+    /// Scrapes got away with on the way down to the pad
+    ScrapeArtist,
+    Veteran,
+    /// A level that carries pods over finished without losing one
+    FullHangar,
+}
+
+// How many rescues in a row, scrapes in one descent and level to reach
+const STEADY_RESCUES: u32 = 5;
+const ARTIST_SCRAPES: u32 = 20;
+const VETERAN_LEVEL: u32 = 10;
+
+impl Achievement {
+    pub const ALL: [Achievement; 5] = [
+        Achievement::SteadyHands,
+        Achievement::Untouched,
+        Achievement::ScrapeArtist,
+        Achievement::Veteran,
+        Achievement::FullHangar,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Achievement::SteadyHands => "Steady Hands",
+            Achievement::Untouched => "Untouched",
+            Achievement::ScrapeArtist => "Scrape Artist",
+            Achievement::Veteran => "Veteran",
+            Achievement::FullHangar => "Full Hangar",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Achievement::SteadyHands => "Rescue 5 terrans without autodock",
+            Achievement::Untouched => "Finish a level with no near misses",
+            Achievement::ScrapeArtist => "Survive 20 scrapes in one descent",
+            Achievement::Veteran => "Reach level 10",
+            Achievement::FullHangar => "Carry every pod over to a new level",
+        }
+    }
+}
+
+/// A player's achievements, worked out from the game's events
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Achievements {
+    unlocked: Vec<Achievement>,
+    /// Unlocked since they were last announced
+    #[serde(skip)]
+    pub just_unlocked: Vec<Achievement>,
+    // Whether the game being played goes on the player's record
+    #[serde(skip)]
+    counting: bool,
+    // Progress towards them, which starts again each game
+    #[serde(skip)]
+    rescues_by_hand: u32,
+    #[serde(skip)]
+    descent_scrapes: u32,
+    #[serde(skip)]
+    level_near_misses: u32,
+    #[serde(skip)]
+    level_pods_lost: u32,
+}
+
+impl Achievements {
+    pub fn has(&self, achievement: Achievement) -> bool {
+        self.unlocked.contains(&achievement)
+    }
+
+    pub fn count(&self) -> usize {
+        self.unlocked.len()
+    }
+
+    fn unlock(&mut self, achievement: Achievement) {
+        if !self.has(achievement) {
+            self.unlocked.push(achievement);
+            self.just_unlocked.push(achievement);
+        }
+    }
+}
+
+impl Subscriber for Achievements {
+    fn notify(&mut self, event: &Event) {
+        if let EventKind::GameStarted { on_record, .. } = event.kind {
+            self.counting = on_record;
+        }
+        if !self.counting {
+            return;
+        }
+        match event.kind {
+            EventKind::GameStarted { .. } => {
+                self.rescues_by_hand = 0;
+                self.level_near_misses = 0;
+                self.level_pods_lost = 0;
+            }
+            EventKind::PodDropped => self.descent_scrapes = 0,
+            EventKind::NearMiss { .. } => {
+                self.descent_scrapes += 1;
+                self.level_near_misses += 1;
+            }
+            EventKind::Landed => {
+                if self.descent_scrapes >= ARTIST_SCRAPES {
+                    self.unlock(Achievement::ScrapeArtist);
+                }
+            }
+            EventKind::Docked { autodock: true } => self.rescues_by_hand = 0,
+            EventKind::Docked { autodock: false } => {
+                self.rescues_by_hand += 1;
+                if self.rescues_by_hand >= STEADY_RESCUES {
+                    self.unlock(Achievement::SteadyHands);
+                }
+            }
+            EventKind::PodDestroyed { .. } => {
+                self.descent_scrapes = 0;
+                self.level_pods_lost += 1;
+            }
+            EventKind::LevelComplete {
+                level,
+                carry_over_pods,
+            } => {
+                if self.level_near_misses == 0 {
+                    self.unlock(Achievement::Untouched);
+                }
+                if carry_over_pods && self.level_pods_lost == 0 {
+                    self.unlock(Achievement::FullHangar);
+                }
+                // Only by getting there, not by starting there
+                if level >= VETERAN_LEVEL - 1 {
+                    self.unlock(Achievement::Veteran);
+                }
+                self.level_near_misses = 0;
+                self.level_pods_lost = 0;
+            }
+            EventKind::RescueeBoarded
+            | EventKind::Launched
+            | EventKind::BonusAwarded { .. }
+            | EventKind::GameOver { .. }
+            | EventKind::GameQuit { .. }
+            | EventKind::AchievementUnlocked { .. } => {}
+        }
+    }
+}
+
+/// Works the current player's achievements out from the game's events,
+/// saving each new one and sending it back for the game to announce
+pub struct AchievementsRecorder {
+    profiles: Rc<RefCell<Profiles>>,
+    announce: Sender<Achievement>,
+}
+
+impl AchievementsRecorder {
+    pub fn new(
+        profiles: Rc<RefCell<Profiles>>,
+        announce: Sender<Achievement>,
+    ) -> AchievementsRecorder {
+        AchievementsRecorder { profiles, announce }
+    }
+}
+
+impl Subscriber for AchievementsRecorder {
+    fn notify(&mut self, event: &Event) {
+        let mut profiles = self.profiles.borrow_mut();
+        let achievements = &mut profiles.current_mut().achievements;
+        achievements.notify(event);
+        if achievements.just_unlocked.is_empty() {
+            return;
+        }
+        for achievement in achievements.just_unlocked.drain(..) {
+            // Once the game has gone there's no one left to tell
+            self.announce.send(achievement).ok();
+        }
+        if let Err(e) = profiles.save() {
+            eprintln!("Couldn't save profiles: {}", e);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use crate::bot::Skill;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stages_go_round_once() {
+        let mut stage = Stage::Demo;
+        let mut shown = 1;
+        while let Some(next) = stage.next() {
+            stage = next;
+            shown += 1;
+        }
+        assert!(stage == Stage::Controls);
+        assert!(shown == 3);
+    }
+}
+
+/// What attract mode shows, one after another, once the title screen has
+/// been left alone for a while
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Stage {
+    /// A bot plays a level
+    Demo,
+    HighScores,
+    Controls,
+}
+
+impl Stage {
+    /// The stage after this one, or None to go back to the title screen
+    pub fn next(&self) -> Option<Stage> {
+        match self {
+            Stage::Demo => Some(Stage::HighScores),
+            Stage::HighScores => Some(Stage::Controls),
+            Stage::Controls => None,
+        }
+    }
+
+    /// The longest a stage is shown for. A demo finishes sooner if its level
+    /// does.
+    pub fn seconds(&self) -> u32 {
+        match self {
+            Stage::Demo => 45,
+            Stage::HighScores | Stage::Controls => 8,
+        }
+    }
+}
+
+// Seconds on the title screen without a key being pressed before the demo
+pub const IDLE_SECONDS: u32 = 20;
+// The demo plays one of the first few levels, picked at random
+pub const DEMO_LEVELS: u32 = 3;
+// Good enough to get somewhere, but not so good it never gets into trouble
+pub const DEMO_SKILL: Skill = Skill::Pilot;
+
+/// Keys listed on the controls screen
+pub const CONTROLS: [(&str, &str); 9] = [
+    ("Down or Space", "Drop a pod"),
+    ("Left and Right", "Steer the pod"),
+    ("Up", "Take off from the landing pad"),
+    ("P", "Pause"),
+    ("M", "Mute the music"),
+    ("O", "Options"),
+    ("S and A", "Statistics, achievements"),
+    ("E", "Level editor"),
+    ("Q", "Quit"),
+];
@@ -0,0 +1,419 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::mpsc::Sender;
+
+use rand::Rng;
+use sfml::audio::{listener, Music, Sound, SoundBuffer, SoundSource, SoundStatus};
+use sfml::cpp::FBox;
+
+use crate::beeper;
+use crate::events::{Event, EventKind, Subscriber};
+use crate::game::{SoundEvent, Sounds};
+use crate::music::{Layer, MusicContext, MusicDirector, MusicLibrary, Stem};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_downmix() {
+        assert!(downmix(&[100, 200, -100, 100], 2) == vec![150, 0]);
+        assert!(downmix(&[i16::MAX, i16::MAX], 2) == vec![i16::MAX]);
+        assert!(downmix(&[1, 2, 3], 1) == vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_pan_position() {
+        let (x, _, z) = pan_position(0.0);
+        assert!(x == 0.0 && z == -1.0);
+        let (x, _, z) = pan_position(-1.0);
+        assert!(x == -1.0 && z.abs() < 0.0001);
+        // out of range values are clamped
+        let (x, _, _) = pan_position(3.0);
+        assert!(x == 1.0);
+    }
+
+    #[test]
+    fn test_volume_levels() {
+        let mut settings = AudioSettings::default();
+        assert!(settings.master == MAX_LEVEL);
+        settings.master = 5;
+        assert!(level_to_volume(settings.master) == 50.0);
+        assert!(level_to_volume(0) == 0.0);
+        assert!(change_level(MAX_LEVEL, 1) == MAX_LEVEL);
+        assert!(change_level(0, -1) == 0);
+        assert!(change_level(4, 1) == 5);
+    }
+
+    #[test]
+    fn test_vary() {
+        for _ in 0..100 {
+            let v = vary(1.0);
+            assert!((1.0 - VARIATION..=1.0 + VARIATION).contains(&v));
+        }
+    }
+
+    #[test]
+    fn test_silent_backend() {
+        let bank = SoundBank::silent();
+        let mut audio = AudioManager::new(&bank);
+        assert!(audio.is_silent());
+        // Nothing to play, but nothing panics either
+        audio.play(&SoundEvent {
+            sound: Sounds::Explosion,
+            pan: -1.0,
+            intensity: 1.0,
+            pitch: 1.0,
+        });
+        audio.toggle_music();
+        audio.update(MusicContext::Level(1), 1.0, false);
+    }
+}
+
+/// Volume settings run from zero to this in whole steps
+pub const MAX_LEVEL: u8 = 10;
+
+#[derive(Clone, Copy)]
+pub struct AudioSettings {
+    pub master: u8,
+    pub music: u8,
+    pub sfx: u8,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            master: MAX_LEVEL,
+            music: MAX_LEVEL,
+            sfx: MAX_LEVEL,
+        }
+    }
+}
+
+pub fn change_level(level: u8, delta: i32) -> u8 {
+    (level as i32 + delta).clamp(0, MAX_LEVEL as i32) as u8
+}
+
+// SFML volumes are percentages
+fn level_to_volume(level: u8) -> f32 {
+    level as f32 * 100.0 / MAX_LEVEL as f32
+}
+
+/// Averages interleaved channels down to mono. SFML only spatialises
+/// mono buffers, so this is needed for panning to work.
+pub fn downmix(samples: &[i16], channels: u32) -> Vec<i16> {
+    let channels = channels.max(1) as usize;
+    samples
+        .chunks(channels)
+        .map(|frame| (frame.iter().map(|s| *s as i32).sum::<i32>() / frame.len() as i32) as i16)
+        .collect()
+}
+
+/// How far across a window `width` wide `x` is, from -1.0 (left) to 1.0
+/// (right)
+pub fn pan(x: f32, width: f32) -> f32 {
+    (x / width * 2.0 - 1.0).clamp(-1.0, 1.0)
+}
+
+/// Converts a pan from -1.0 (left) to 1.0 (right) into a listener-relative
+/// position one unit away, so distance attenuation doesn't change the volume
+pub fn pan_position(pan: f32) -> (f32, f32, f32) {
+    let x = pan.clamp(-1.0, 1.0);
+    (x, 0.0, -(1.0 - x * x).sqrt())
+}
+
+/// Guesses whether there is anything to play sound through. Plain WSL2 has
+/// no audio device and OpenAL can crash when it tries to open one.
+fn audio_device_available() -> bool {
+    if !cfg!(target_os = "linux") {
+        return true;
+    }
+    let runtime_dir = std::env::var("XDG_RUNTIME_DIR").unwrap_or_default();
+    Path::new("/dev/snd").exists()
+        || std::env::var_os("PULSE_SERVER").is_some()
+        || Path::new(&(runtime_dir.clone() + "/pulse/native")).exists()
+        || Path::new(&(runtime_dir + "/pipewire-0")).exists()
+}
+
+/// Owns the sample data for every sound so that an `AudioManager` can
+/// borrow it. Anything that fails to load is left out and stays silent.
+pub struct SoundBank {
+    buffers: HashMap<Sounds, FBox<SoundBuffer>>,
+    music: MusicLibrary,
+    sting: Option<FBox<SoundBuffer>>,
+}
+
+impl SoundBank {
+    pub fn load(resource_path: &str, use_beeper: bool, silent: bool) -> SoundBank {
+        if silent || !audio_device_available() {
+            return SoundBank::silent();
+        }
+        let files = [
+            (Sounds::Explosion, "explosion.wav"),
+            (Sounds::Scrape, "scrape.wav"),
+            (Sounds::Landed, "success.wav"),
+            (Sounds::Docked, "docked.wav"),
+            (Sounds::Seatbelt, "seatbelt.wav"),
+            (Sounds::TakeOff, "take_off.wav"),
+            (Sounds::Bonus, "bonus.wav"),
+            (Sounds::DropPod, "drop_pod.wav"),
+        ];
+        let mut buffers = HashMap::new();
+        for (sound, file) in files {
+            let samples = if use_beeper {
+                Some((beeper::effect(&sound), beeper::SAMPLE_RATE))
+            } else {
+                SoundBuffer::from_file(&(resource_path.to_string() + "/" + file))
+                    .ok()
+                    .map(|buffer| {
+                        let mono = downmix(buffer.samples(), buffer.channel_count());
+                        (mono, buffer.sample_rate())
+                    })
+            };
+            match samples.and_then(|(s, rate)| SoundBuffer::from_samples(&s, 1, rate).ok()) {
+                Some(buffer) => {
+                    buffers.insert(sound, buffer);
+                }
+                None => eprintln!("Could not load {}, it will be silent", file),
+            }
+        }
+        let music = MusicLibrary::load(resource_path, use_beeper);
+        let sting = music
+            .sting
+            .as_ref()
+            .and_then(|s| SoundBuffer::from_samples(s, 1, beeper::SAMPLE_RATE).ok());
+        SoundBank {
+            buffers,
+            music,
+            sting,
+        }
+    }
+
+    pub fn silent() -> SoundBank {
+        SoundBank {
+            buffers: HashMap::new(),
+            music: MusicLibrary::empty(),
+            sting: None,
+        }
+    }
+}
+
+// More than this many sounds at once and the oldest is cut off
+const MAX_VOICES: usize = 16;
+// Random change to each sound's pitch and volume so repeats don't sound identical
+const VARIATION: f32 = 0.04;
+
+fn vary(value: f32) -> f32 {
+    value * rand::thread_rng().gen_range(1.0 - VARIATION..=1.0 + VARIATION)
+}
+
+/// Plays sound effects and layered music from a `SoundBank`, applying
+/// the volume settings and panning each effect left or right
+pub struct AudioManager<'a> {
+    bank: &'a SoundBank,
+    voices: Vec<(Sounds, Sound<'a>)>,
+    director: MusicDirector,
+    // The current track's stems, all looping together
+    stems: Vec<Option<Music<'a>>>,
+    sting: Option<Sound<'a>>,
+    music_muted: bool,
+    settings: AudioSettings,
+}
+
+impl<'a> AudioManager<'a> {
+    pub fn new(bank: &'a SoundBank) -> AudioManager<'a> {
+        AudioManager {
+            bank,
+            voices: Vec::new(),
+            director: MusicDirector::new(),
+            stems: Vec::new(),
+            sting: bank.sting.as_ref().map(|buffer| Sound::with_buffer(buffer)),
+            music_muted: false,
+            settings: AudioSettings::default(),
+        }
+    }
+
+    /// True when nothing at all can be heard
+    pub fn is_silent(&self) -> bool {
+        self.bank.buffers.is_empty() && self.bank.music.tracks.is_empty()
+    }
+
+    pub fn apply_settings(&mut self, settings: AudioSettings) {
+        self.settings = settings;
+        if self.is_silent() {
+            return;
+        }
+        listener::set_global_volume(level_to_volume(settings.master));
+        if let Some(sting) = self.sting.as_mut() {
+            sting.set_volume(level_to_volume(settings.music));
+        }
+    }
+
+    fn start_track(&mut self, track: usize) {
+        self.stems.clear();
+        let Some(track) = self.bank.music.tracks.get(track) else {
+            return;
+        };
+        for stem in &track.stems {
+            let music = match stem {
+                None => None,
+                Some(Stem::File(file)) => Music::from_file(file).ok(),
+                Some(Stem::Memory(data)) => Music::from_memory(data).ok(),
+            };
+            self.stems.push(music.map(|mut music| {
+                music.set_looping(true);
+                music.set_volume(0.0);
+                music
+            }));
+        }
+        if !self.music_muted {
+            // Start them together so the layers stay in time
+            for music in self.stems.iter_mut().flatten() {
+                music.play();
+            }
+        }
+    }
+
+    /// Plays an effect with the event's position, loudness and pitch, plus
+    /// a little random variation
+    pub fn play(&mut self, event: &SoundEvent) {
+        let Some(buffer) = self.bank.buffers.get(&event.sound) else {
+            return;
+        };
+        self.voices
+            .retain(|(_, voice)| voice.status() != SoundStatus::STOPPED);
+        let pitch = vary(event.pitch);
+        // Scrapes come every frame during a near miss so rather than
+        // restarting, bend the one already playing
+        if event.sound == Sounds::Scrape {
+            if let Some((_, voice)) = self.voices.iter_mut().find(|(s, _)| *s == Sounds::Scrape) {
+                voice.set_pitch(pitch);
+                return;
+            }
+        }
+        if event.sound == Sounds::Explosion {
+            self.director.duck();
+        }
+        if self.voices.len() >= MAX_VOICES {
+            self.voices.remove(0);
+        }
+        let volume = level_to_volume(self.settings.sfx) * event.intensity.clamp(0.0, 1.0);
+        let mut voice = Sound::with_buffer(buffer);
+        voice.set_volume(vary(volume).min(100.0));
+        voice.set_pitch(pitch);
+        voice.set_relative_to_listener(true);
+        voice.set_attenuation(0.0);
+        voice.set_position(pan_position(event.pan));
+        voice.play();
+        self.voices.push((event.sound, voice));
+    }
+
+    pub fn toggle_music(&mut self) {
+        self.music_muted = !self.music_muted;
+        for music in self.stems.iter_mut().flatten() {
+            if self.music_muted {
+                music.pause();
+            } else {
+                music.play();
+            }
+        }
+    }
+
+    /// Call once a frame with what is going on in the game. Gameplay
+    /// intensity, from 0.0 to 1.0, brings in the music's extra layers.
+    pub fn update(&mut self, context: MusicContext, intensity: f32, paused: bool) {
+        if let Some(track) = self.director.set_context(context, &self.bank.music) {
+            self.start_track(track);
+        } else if self.director.current_track().is_none() {
+            self.stems.clear();
+        }
+        if self.director.take_sting() {
+            if let Some(sting) = self.sting.as_mut() {
+                sting.play();
+                self.director.duck();
+            }
+        }
+        self.director.update(intensity);
+        let layers = [Layer::Calm, Layer::Drive, Layer::Intense];
+        for (music, layer) in self.stems.iter_mut().zip(layers) {
+            let Some(music) = music else {
+                continue;
+            };
+            music.set_volume(
+                level_to_volume(self.settings.music) * self.director.layer_volume(layer),
+            );
+            if paused {
+                music.pause();
+            } else if music.status() != SoundStatus::PLAYING && !self.music_muted {
+                music.play();
+            }
+        }
+    }
+}
+
+/// Picks a sound for each of the game's events, sending it off for an
+/// `AudioManager` to play
+pub struct SoundCues {
+    sounds: Sender<SoundEvent>,
+    window_width: f32,
+    // Docking bonuses in a row, each chiming higher than the last
+    bonus_streak: u32,
+}
+
+impl SoundCues {
+    pub fn new(sounds: Sender<SoundEvent>, window_width: f32) -> SoundCues {
+        SoundCues {
+            sounds,
+            window_width,
+            bonus_streak: 0,
+        }
+    }
+}
+
+impl Subscriber for SoundCues {
+    fn notify(&mut self, event: &Event) {
+        let (sound, intensity, pitch) = match event.kind {
+            EventKind::PodDropped => (Sounds::DropPod, 1.0, 1.0),
+            EventKind::Landed => (Sounds::Landed, 1.0, 1.0),
+            EventKind::RescueeBoarded => (Sounds::Seatbelt, 1.0, 1.0),
+            EventKind::Launched => (Sounds::TakeOff, 1.0, 1.0),
+            EventKind::Docked { autodock } => {
+                if autodock {
+                    self.bonus_streak = 0;
+                }
+                (Sounds::Docked, 1.0, 1.0)
+            }
+            EventKind::BonusAwarded { .. } => {
+                self.bonus_streak += 1;
+                let pitch = (1.0 + 0.1 * (self.bonus_streak - 1) as f32).min(2.0);
+                (Sounds::Bonus, 1.0, pitch)
+            }
+            EventKind::PodDestroyed {
+                from_mothership, ..
+            } => {
+                self.bonus_streak = 0;
+                // explosions further down, away from the mothership, sound
+                // more distant
+                (Sounds::Explosion, 1.0 - 0.6 * from_mothership.min(1.0), 1.0)
+            }
+            EventKind::GameStarted { .. } => {
+                self.bonus_streak = 0;
+                return;
+            }
+            EventKind::NearMiss { .. }
+            | EventKind::LevelComplete { .. }
+            | EventKind::GameOver { .. }
+            | EventKind::GameQuit { .. }
+            | EventKind::AchievementUnlocked { .. } => return,
+        };
+        // Once the game has gone there's no one left to hear it
+        self.sounds
+            .send(SoundEvent {
+                sound,
+                pan: pan(event.x, self.window_width),
+                intensity,
+                pitch,
+            })
+            .ok();
+    }
+}
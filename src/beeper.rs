@@ -0,0 +1,375 @@
+use crate::game::Sounds;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zero_crossings(samples: &[i16]) -> usize {
+        samples
+            .windows(2)
+            .filter(|w| (w[0] >= 0) != (w[1] >= 0))
+            .count()
+    }
+
+    #[test]
+    fn test_square_wave() {
+        let samples = square(441.0, 1.0, 0.5);
+        assert!(samples.len() == SAMPLE_RATE as usize);
+        // two crossings per cycle
+        let crossings = zero_crossings(&samples);
+        assert!((880..=883).contains(&crossings));
+        assert!(samples.iter().all(|s| s.abs() == amplitude(0.5)));
+    }
+
+    #[test]
+    fn test_sweep_rises() {
+        let samples = sweep(100.0, 1000.0, 1.0, 0.5);
+        let half = samples.len() / 2;
+        assert!(zero_crossings(&samples[half..]) > zero_crossings(&samples[..half]) * 2);
+    }
+
+    #[test]
+    fn test_noise_is_repeatable_and_decays() {
+        let a = noise(0.5, 0.8, 1);
+        let b = noise(0.5, 0.8, 1);
+        assert!(a == b);
+        assert!(a.iter().any(|s| *s > 0) && a.iter().any(|s| *s < 0));
+        let peak = |s: &[i16]| s.iter().map(|v| v.unsigned_abs()).max().unwrap();
+        assert!(peak(&a[a.len() - 1000..]) < peak(&a[..1000]));
+    }
+
+    #[test]
+    fn test_note_frequency() {
+        assert!(note_frequency("A4") == Some(440.0));
+        assert!((note_frequency("C4").unwrap() - 261.63).abs() < 0.01);
+        assert!((note_frequency("F#5").unwrap() - 739.99).abs() < 0.01);
+        assert!((note_frequency("Bb3").unwrap() - 233.08).abs() < 0.01);
+        assert!(note_frequency("-") == Some(0.0));
+        assert!(note_frequency("H2").is_none());
+        assert!(note_frequency("C").is_none());
+    }
+
+    #[test]
+    fn test_tune() {
+        let samples = tune(&[note("C5", 0.25), note("-", 0.25), note("G5", 0.5)], 0.5);
+        assert!(samples.len() == SAMPLE_RATE as usize);
+        // the rest is silent
+        let quarter = samples.len() / 4;
+        assert!(samples[quarter..quarter * 2].iter().all(|s| *s == 0));
+    }
+
+    #[test]
+    fn test_effects_are_not_silent() {
+        for sound in [
+            Sounds::Explosion,
+            Sounds::Landed,
+            Sounds::Docked,
+            Sounds::Seatbelt,
+            Sounds::TakeOff,
+            Sounds::DropPod,
+            Sounds::Scrape,
+            Sounds::Bonus,
+        ] {
+            let samples = effect(&sound);
+            assert!(!samples.is_empty());
+            assert!(samples.iter().any(|s| *s != 0));
+        }
+    }
+
+    #[test]
+    fn test_music_stems_loop_together() {
+        let len = music("bass").unwrap().len();
+        for name in ["arpeggio", "drums", "lament"] {
+            assert!(music(name).unwrap().len() == len);
+        }
+        assert!(music("sting").is_some());
+        assert!(music("kazoo").is_none());
+    }
+
+    #[test]
+    fn test_wav_bytes() {
+        let bytes = wav_bytes(&[0, 1, -1]);
+        assert!(bytes.len() == 44 + 6);
+        assert!(&bytes[0..4] == b"RIFF");
+        assert!(&bytes[8..12] == b"WAVE");
+        assert!(u32::from_le_bytes(bytes[40..44].try_into().unwrap()) == 6);
+        assert!(i16::from_le_bytes([bytes[48], bytes[49]]) == -1);
+    }
+}
+
+/// Sample rate of everything generated here (mono, 16-bit)
+pub const SAMPLE_RATE: u32 = 44100;
+
+#[derive(Clone, Copy)]
+pub struct Note {
+    /// In Hz, with zero meaning a rest
+    pub frequency: f32,
+    /// In seconds
+    pub duration: f32,
+}
+
+/// Builds a note from its name, e.g. "C4", "F#5", "Bb3", or "-" for a rest.
+/// Panics on a bad name as tunes are written in the code.
+pub fn note(name: &str, duration: f32) -> Note {
+    Note {
+        frequency: note_frequency(name).unwrap_or_else(|| panic!("bad note name '{}'", name)),
+        duration,
+    }
+}
+
+pub fn note_frequency(name: &str) -> Option<f32> {
+    if name == "-" {
+        return Some(0.0);
+    }
+    let mut chars = name.chars();
+    let semitone = match chars.next()? {
+        'C' => -9,
+        'D' => -7,
+        'E' => -5,
+        'F' => -4,
+        'G' => -2,
+        'A' => 0,
+        'B' => 2,
+        _ => return None,
+    };
+    let rest = chars.as_str();
+    let (accidental, octave) = match rest.chars().next()? {
+        '#' => (1, &rest[1..]),
+        'b' => (-1, &rest[1..]),
+        _ => (0, rest),
+    };
+    let octave: i32 = octave.parse().ok()?;
+    let from_a4 = semitone + accidental + (octave - 4) * 12;
+    Some(440.0 * 2_f32.powf(from_a4 as f32 / 12.0))
+}
+
+fn amplitude(volume: f32) -> i16 {
+    (volume.clamp(0.0, 1.0) * i16::MAX as f32 * 0.5) as i16
+}
+
+fn sample_count(duration: f32) -> usize {
+    (duration * SAMPLE_RATE as f32) as usize
+}
+
+/// A square wave sliding from one frequency to another, like the beeper
+/// being toggled in a loop with a changing delay
+pub fn sweep(from: f32, to: f32, duration: f32, volume: f32) -> Vec<i16> {
+    let count = sample_count(duration);
+    let amp = amplitude(volume);
+    let mut phase = 0.0_f32;
+    (0..count)
+        .map(|n| {
+            let frequency = from + (to - from) * n as f32 / count as f32;
+            phase = (phase + frequency / SAMPLE_RATE as f32).fract();
+            if phase < 0.5 {
+                amp
+            } else {
+                -amp
+            }
+        })
+        .collect()
+}
+
+pub fn square(frequency: f32, duration: f32, volume: f32) -> Vec<i16> {
+    sweep(frequency, frequency, duration, volume)
+}
+
+/// White noise that fades out. Each random level is held for `hold`
+/// samples, so larger values give a lower, rumbling sound.
+pub fn noise_with_hold(duration: f32, volume: f32, hold: usize, seed: u32) -> Vec<i16> {
+    let count = sample_count(duration);
+    let amp = amplitude(volume) as f32;
+    // xorshift keeps the noise repeatable without needing an rng
+    let mut state = seed.max(1);
+    let mut level = 1.0;
+    (0..count)
+        .map(|n| {
+            if n % hold.max(1) == 0 {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                level = if state & 1 == 0 { 1.0 } else { -1.0 };
+            }
+            let fade = 1.0 - n as f32 / count as f32;
+            (level * amp * fade) as i16
+        })
+        .collect()
+}
+
+pub fn noise(duration: f32, volume: f32, seed: u32) -> Vec<i16> {
+    noise_with_hold(duration, volume, 1, seed)
+}
+
+/// Plays a sequence of notes with a short gap between each so repeated
+/// notes can be heard separately
+pub fn tune(notes: &[Note], volume: f32) -> Vec<i16> {
+    let mut samples = Vec::new();
+    for n in notes {
+        let total = sample_count(n.duration);
+        if n.frequency > 0.0 {
+            let gap = (total / 10).min(sample_count(0.01));
+            samples.extend(
+                square(n.frequency, n.duration, volume)
+                    .into_iter()
+                    .take(total - gap),
+            );
+            samples.resize(samples.len() + gap, 0);
+        } else {
+            samples.resize(samples.len() + total, 0);
+        }
+    }
+    samples
+}
+
+/// Generates the beeper version of one of the game's sound effects
+pub fn effect(sound: &Sounds) -> Vec<i16> {
+    match sound {
+        Sounds::Explosion => {
+            let mut samples = noise_with_hold(0.3, 0.9, 4, 7);
+            samples.extend(noise_with_hold(0.6, 0.6, 12, 11));
+            samples
+        }
+        Sounds::Scrape => noise(0.15, 0.5, 3),
+        Sounds::Landed => tune(
+            &[
+                note("C5", 0.08),
+                note("E5", 0.08),
+                note("G5", 0.08),
+                note("C6", 0.2),
+            ],
+            0.5,
+        ),
+        Sounds::Docked => tune(&[note("G5", 0.1), note("C6", 0.25)], 0.5),
+        Sounds::Seatbelt => tune(&[note("D7", 0.03), note("-", 0.06), note("D7", 0.03)], 0.4),
+        Sounds::TakeOff => sweep(150.0, 900.0, 1.0, 0.4),
+        Sounds::DropPod => sweep(900.0, 200.0, 0.35, 0.4),
+        Sounds::Bonus => tune(
+            &[
+                note("C6", 0.05),
+                note("E6", 0.05),
+                note("G6", 0.05),
+                note("C7", 0.05),
+                note("C6", 0.05),
+                note("E6", 0.05),
+                note("G6", 0.05),
+                note("C7", 0.15),
+            ],
+            0.4,
+        ),
+    }
+}
+
+// The music stems all share this chord sequence and length so that they
+// stay in step when looped together
+const CHORDS: [[&str; 3]; 4] = [
+    ["A2", "E3", "A3"],
+    ["A2", "E3", "A3"],
+    ["F2", "C3", "F3"],
+    ["G2", "D3", "G3"],
+];
+const BEAT: f32 = 0.2;
+
+/// A short looping bass line to stand in for background.wav
+pub fn background_tune() -> Vec<i16> {
+    let notes: Vec<Note> = CHORDS
+        .iter()
+        .flat_map(|[root, fifth, octave]| {
+            [
+                note(root, BEAT),
+                note(fifth, BEAT),
+                note(octave, BEAT),
+                note(fifth, BEAT),
+            ]
+        })
+        .collect();
+    tune(&notes, 0.25)
+}
+
+// Two octaves up and twice as fast as the bass line
+fn arpeggio() -> Vec<i16> {
+    let up = |name: &str| {
+        let (pitch, octave) = name.split_at(name.len() - 1);
+        format!("{}{}", pitch, octave.parse::<i32>().unwrap_or(4) + 2)
+    };
+    let notes: Vec<Note> = CHORDS
+        .iter()
+        .flat_map(|chord| {
+            let [root, fifth, octave] = chord.map(up);
+            [
+                &root, &fifth, &octave, &fifth, &root, &fifth, &octave, &fifth,
+            ]
+            .map(|name| note(name, BEAT / 2.0))
+        })
+        .collect();
+    tune(&notes, 0.15)
+}
+
+// A noise "hi-hat" on every beat
+fn drums() -> Vec<i16> {
+    let mut samples = Vec::new();
+    for beat in 0..CHORDS.len() * 4 {
+        let hit = noise(BEAT / 5.0, 0.3, beat as u32 + 1);
+        samples.extend(&hit);
+        samples.resize(samples.len() + sample_count(BEAT) - hit.len(), 0);
+    }
+    samples
+}
+
+fn lament() -> Vec<i16> {
+    tune(
+        &[
+            note("A2", BEAT * 4.0),
+            note("G2", BEAT * 4.0),
+            note("F2", BEAT * 4.0),
+            note("E2", BEAT * 4.0),
+        ],
+        0.2,
+    )
+}
+
+fn sting() -> Vec<i16> {
+    tune(
+        &[
+            note("A4", 0.1),
+            note("C5", 0.1),
+            note("E5", 0.1),
+            note("A5", 0.4),
+        ],
+        0.4,
+    )
+}
+
+/// Generated music for the playlists: "bass", "arpeggio", "drums" and
+/// "lament" loop in step with each other, "sting" is a one-off jingle
+pub fn music(name: &str) -> Option<Vec<i16>> {
+    match name {
+        "bass" => Some(background_tune()),
+        "arpeggio" => Some(arpeggio()),
+        "drums" => Some(drums()),
+        "lament" => Some(lament()),
+        "sting" => Some(sting()),
+        _ => None,
+    }
+}
+
+/// Wraps samples in a WAV header so they can be streamed like a file
+pub fn wav_bytes(samples: &[i16]) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16_u32.to_le_bytes());
+    bytes.extend_from_slice(&1_u16.to_le_bytes()); // PCM
+    bytes.extend_from_slice(&1_u16.to_le_bytes()); // mono
+    bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    bytes.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    bytes.extend_from_slice(&2_u16.to_le_bytes());
+    bytes.extend_from_slice(&16_u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        bytes.extend_from_slice(&sample.to_le_bytes());
+    }
+    bytes
+}
@@ -0,0 +1,284 @@
+use std::ops::Range;
+
+use crate::difficulty::{Difficulty, Preset};
+use crate::events::EventKind;
+use crate::game::{move_mothership, Game, GameStatus, PodStatus};
+use crate::solver::{self, Field};
+use crate::timeattack::FRAMES_PER_SECOND;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ace_gets_through_the_first_levels() {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        game.set_seed(1);
+        game.restart();
+        game.play();
+        let outcome = play(
+            &mut game,
+            &mut Bot::new(Skill::Ace),
+            3 * 60 * FRAMES_PER_SECOND,
+        );
+        assert!(outcome.levels_completed >= 3);
+        assert!(outcome.pods_lost <= 1);
+    }
+
+    #[test]
+    fn test_skill_names() {
+        for skill in Skill::ALL {
+            assert!(Skill::from_name(&skill.name().to_lowercase()) == Some(skill));
+        }
+        assert!(Skill::from_name("hotshot").is_none());
+        // Better pilots react sooner and see further
+        assert!(Skill::Ace.reaction_frames() < Skill::Novice.reaction_frames());
+        assert!(Skill::Ace.look_ahead_frames() > Skill::Novice.look_ahead_frames());
+    }
+}
+
+/// How good a pilot the bot is
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Skill {
+    Novice,
+    Pilot,
+    Ace,
+}
+
+impl Skill {
+    pub const ALL: [Skill; 3] = [Skill::Novice, Skill::Pilot, Skill::Ace];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Skill::Novice => "Novice",
+            Skill::Pilot => "Pilot",
+            Skill::Ace => "Ace",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Skill> {
+        Skill::ALL
+            .into_iter()
+            .find(|skill| skill.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Frames between looking at the screen, holding the controls as they
+    /// were in between
+    pub fn reaction_frames(&self) -> u32 {
+        match self {
+            Skill::Novice => 15,
+            Skill::Pilot => 6,
+            Skill::Ace => 1,
+        }
+    }
+
+    /// How many frames ahead the bot can see where the asteroids are going
+    pub fn look_ahead_frames(&self) -> u32 {
+        match self {
+            Skill::Novice => 20,
+            Skill::Pilot => 45,
+            Skill::Ace => 120,
+        }
+    }
+}
+
+// How much further from each blob the bot keeps than it strictly has to, as
+// a fraction of its radius
+const MARGIN: f32 = 0.15;
+// The pod steers at most this many pixels a frame
+const MAX_DELTA: i8 = 4;
+// Only drop when the pad is well within reach on the way down
+const REACH: f32 = 0.7;
+
+/// A computer pilot. It only uses what a player could see on screen and the
+/// same controls a player has.
+pub struct Bot {
+    skill: Skill,
+    // Frames until it next looks at the screen
+    wait: u32,
+}
+
+impl Bot {
+    pub fn new(skill: Skill) -> Bot {
+        Bot { skill, wait: 0 }
+    }
+
+    /// Works the controls for one frame. Call it before the pod is moved and
+    /// the game goes on to the next frame.
+    pub fn fly(&mut self, game: &mut Game) {
+        if game.game_status() != GameStatus::Playing {
+            return;
+        }
+        if self.wait > 0 {
+            self.wait -= 1;
+            return;
+        }
+        self.wait = self.skill.reaction_frames() - 1;
+        let mut field = game.field();
+        field.hit_scale += MARGIN;
+        match game.pod_status() {
+            PodStatus::Inactive | PodStatus::Exploding => {
+                let drop = (
+                    field.mothership_x + 25.0 + field.mothership_direction as f32,
+                    field.mothership_y + 30.0,
+                );
+                if self.worth_dropping(&field, drop) {
+                    game.drop_pod();
+                    self.steer(game, &field, false);
+                }
+            }
+            PodStatus::Dropping => self.steer(game, &field, false),
+            PodStatus::ReadyForTakeOff => {
+                let pod = game.pod_position();
+                let delta = self.towards(&field, pod, true);
+                if self.safe(&field, pod, true, delta) {
+                    game.launch_pod();
+                    game.pod_set_delta(delta);
+                }
+            }
+            PodStatus::Ascending => self.steer(game, &field, true),
+            PodStatus::AutoDock => {}
+        }
+    }
+
+    // Whether a pod dropped now could get to the pad and stay clear of
+    // everything as far as the bot can see
+    fn worth_dropping(&self, field: &Field, (x, y): (f32, f32)) -> bool {
+        let m = field.size_multiplier;
+        let frames = (field.landing_y - y) / (5.0 * m);
+        let reach = REACH * frames * MAX_DELTA as f32 * m;
+        if (self.target_x(field, (x, y), false) - x).abs() > reach {
+            return false;
+        }
+        let delta = self.towards(field, (x, y), false);
+        self.safe(field, (x, y), false, delta)
+    }
+
+    // Where the pod wants to be across the screen: the middle of the pad on
+    // the way down, and wherever the mothership will be on the way up
+    fn target_x(&self, field: &Field, (_, y): (f32, f32), rising: bool) -> f32 {
+        if !rising {
+            return field.landing_pad_x + (field.landing_pad_width - field.pod_size) / 2.0;
+        }
+        let frames = ((y - field.mothership_y - 10.0) / (5.0 * field.size_multiplier)).max(0.0);
+        let (mut x, mut direction) = (field.mothership_x, field.mothership_direction);
+        for _ in 0..frames as u32 {
+            (x, direction) = move_mothership(
+                x,
+                direction,
+                field.width,
+                field.mothership_width,
+                field.size_multiplier,
+            );
+        }
+        x + (field.mothership_width - field.pod_size) / 2.0
+    }
+
+    fn towards(&self, field: &Field, pod: (f32, f32), rising: bool) -> i8 {
+        let distance = (self.target_x(field, pod, rising) - pod.0) / field.size_multiplier;
+        distance.round().clamp(-MAX_DELTA as f32, MAX_DELTA as f32) as i8
+    }
+
+    fn safe(&self, field: &Field, pod: (f32, f32), rising: bool, delta: i8) -> bool {
+        solver::can_survive(
+            field,
+            pod,
+            rising,
+            (
+                delta as f32 * field.size_multiplier,
+                self.skill.reaction_frames(),
+            ),
+            self.skill.look_ahead_frames(),
+        )
+    }
+
+    // Heads for the target, or as near that way as is safe
+    fn steer(&self, game: &mut Game, field: &Field, rising: bool) {
+        let pod = game.pod_position();
+        let wanted = self.towards(field, pod, rising);
+        let mut deltas: Vec<i8> = (-MAX_DELTA..=MAX_DELTA).collect();
+        deltas.sort_by_key(|delta| (delta - wanted).abs());
+        let delta = deltas
+            .into_iter()
+            .find(|delta| self.safe(field, pod, rising, *delta))
+            .unwrap_or(wanted);
+        game.pod_set_delta(delta);
+    }
+}
+
+/// How a bot got on
+#[derive(Debug, Default)]
+pub struct Outcome {
+    pub levels_completed: u32,
+    pub pods_lost: u32,
+    pub score: u32,
+    pub frames: u32,
+}
+
+/// Lets the bot play the game as it stands, without a window, until the
+/// game is over or `frames` frames have gone by
+pub fn play(game: &mut Game, bot: &mut Bot, frames: u32) -> Outcome {
+    let first_level = game.level();
+    let mut outcome = Outcome::default();
+    while outcome.frames < frames {
+        match game.game_status() {
+            GameStatus::Playing => {}
+            GameStatus::NewLevel => game.start(),
+            _ => break,
+        }
+        bot.fly(game);
+        if game.get_pod_delta() != 0 {
+            game.pod_manoeuvre();
+        }
+        game.next_frame();
+        outcome.pods_lost += game
+            .events
+            .iter()
+            .filter(|event| matches!(event.kind, EventKind::PodDestroyed { .. }))
+            .count() as u32;
+        game.sounds_to_play.clear();
+        game.status_changes.clear();
+        game.events.clear();
+        outcome.frames += 1;
+    }
+    outcome.levels_completed = game.level() - first_level;
+    outcome.score = game.score();
+    outcome
+}
+
+/// How far each skill of bot gets on each difficulty, over a range of seeds,
+/// as a yardstick for how hard the game is
+pub fn yardstick(game: &mut Game, seeds: Range<u64>, minutes: f32) -> String {
+    let mut lines = vec![format!(
+        "Seeds {}..{}, {} minutes a game",
+        seeds.start, seeds.end, minutes
+    )];
+    let frames = (minutes * 60.0 * FRAMES_PER_SECOND as f32) as u32;
+    let count = seeds.end.saturating_sub(seeds.start).max(1) as f32;
+    for preset in [Preset::Easy, Preset::Normal, Preset::Hard] {
+        game.set_difficulty(Difficulty::preset(preset));
+        for skill in Skill::ALL {
+            let mut levels = 0;
+            let mut pods_lost = 0;
+            let mut score = 0;
+            for seed in seeds.clone() {
+                game.set_seed(seed);
+                game.restart();
+                game.play();
+                let outcome = play(game, &mut Bot::new(skill), frames);
+                levels += outcome.levels_completed;
+                pods_lost += outcome.pods_lost;
+                score += outcome.score;
+            }
+            lines.push(format!(
+                "{} on {}: {:.1} levels, {:.1} pods lost, {:.0} points on average",
+                skill.name(),
+                preset.name(),
+                levels as f32 / count,
+                pods_lost as f32 / count,
+                score as f32 / count
+            ));
+        }
+    }
+    lines.join("\n")
+}
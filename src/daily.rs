@@ -0,0 +1,101 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_date() {
+        assert!(format_date(0) == "1970-01-01");
+        assert!(format_date(59) == "1970-03-01");
+        assert!(format_date(11_016) == "2000-02-29");
+        assert!(format_date(20_744) == "2026-10-18");
+    }
+
+    #[test]
+    fn test_seeds_differ_by_day() {
+        assert!(seed(20_744) == seed(20_744));
+        assert!(seed(20_744) != seed(20_745));
+    }
+
+    fn result(day: u32) -> DailyResult {
+        DailyResult {
+            day,
+            score: 100,
+            level: 1,
+        }
+    }
+
+    #[test]
+    fn test_streak() {
+        assert!(streak(&[], 10) == 0);
+        let results = vec![result(5), result(7), result(8), result(9)];
+        // Not played yet today, but the streak isn't broken until tomorrow
+        assert!(streak(&results, 10) == 3);
+        assert!(streak(&results, 9) == 3);
+        assert!(streak(&results, 11) == 0);
+        let mut results = results;
+        results.push(result(10));
+        assert!(streak(&results, 10) == 4);
+    }
+}
+
+/// Today's date in UTC, as days since 1 January 1970
+pub fn today() -> u32 {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    (seconds / 86_400) as u32
+}
+
+/// The seed for a day's levels. It comes from the date alone, so everyone
+/// gets the same levels on the same day.
+pub fn seed(day: u32) -> u64 {
+    // Spread the days out so seeds a day apart don't look alike
+    (day as u64 ^ 0x5854_4152_4441).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+}
+
+/// A day as YYYY-MM-DD
+pub fn format_date(day: u32) -> String {
+    // Howard Hinnant's civil_from_days
+    let z = day as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+/// The scored attempt at one day's run
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct DailyResult {
+    /// Days since 1 January 1970
+    pub day: u32,
+    pub score: u32,
+    /// Level the run ended on
+    pub level: u32,
+}
+
+/// How many days in a row have been played, up to today. A streak carries
+/// on through today until today's run has been played.
+pub fn streak(results: &[DailyResult], today: u32) -> u32 {
+    let played = |day: u32| results.iter().any(|r| r.day == day);
+    let mut day = if played(today) {
+        today
+    } else {
+        today.wrapping_sub(1)
+    };
+    let mut streak = 0;
+    while played(day) {
+        streak += 1;
+        day = day.wrapping_sub(1);
+    }
+    streak
+}
@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normal_is_the_original_game() {
+        let normal = Difficulty::preset(Preset::Normal);
+        assert!(normal.scale(Setting::Asteroids, 18.0) == 18.0);
+        assert!(normal.scale(Setting::Speed, 3.5) == 3.5);
+        assert!(normal.hit_scale() == 1.0);
+        assert!(normal.pods(1) == 1);
+        assert!(normal.mothership_speed(10) == 10);
+        assert!(normal.docking_bonus);
+    }
+
+    #[test]
+    fn test_presets_get_harder() {
+        let easy = Difficulty::preset(Preset::Easy);
+        let hard = Difficulty::preset(Preset::Hard);
+        assert!(easy.scale(Setting::Speed, 4.0) < hard.scale(Setting::Speed, 4.0));
+        assert!(easy.pods(2) > hard.pods(2));
+        assert!(easy.hit_scale() < hard.hit_scale());
+        // Always at least one pod, and the mothership keeps moving
+        assert!(hard.pods(0) == 1);
+        assert!(hard.mothership_speed(-1) == -1);
+    }
+
+    #[test]
+    fn test_changing_a_setting_makes_it_custom() {
+        let mut difficulty = Difficulty::preset(Preset::Normal);
+        difficulty.change(Setting::Speed, 1);
+        assert!(difficulty.preset == Preset::Custom);
+        assert!(difficulty.value(Setting::Speed) == 125);
+        assert!(difficulty != Difficulty::preset(Preset::Normal));
+        for _ in 0..100 {
+            difficulty.change(Setting::Tolerance, 1);
+        }
+        assert!(difficulty.value(Setting::Tolerance) == MAX_TOLERANCE);
+        difficulty.change(Setting::DockingBonus, 1);
+        assert!(!difficulty.docking_bonus);
+    }
+
+    #[test]
+    fn test_cycle_presets() {
+        let mut difficulty = Difficulty::preset(Preset::Normal);
+        difficulty.cycle_preset(1);
+        assert!(difficulty == Difficulty::preset(Preset::Hard));
+        difficulty.cycle_preset(1);
+        // Custom starts from wherever things were
+        assert!(difficulty.preset == Preset::Custom);
+        assert!(difficulty.value(Setting::Speed) == Difficulty::preset(Preset::Hard).speed);
+        difficulty.cycle_preset(1);
+        assert!(difficulty == Difficulty::preset(Preset::Easy));
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Preset {
+    Easy,
+    Normal,
+    Hard,
+    Custom,
+}
+
+impl Preset {
+    const ALL: [Preset; 4] = [Preset::Easy, Preset::Normal, Preset::Hard, Preset::Custom];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Easy => "Easy",
+            Preset::Normal => "Normal",
+            Preset::Hard => "Hard",
+            Preset::Custom => "Custom",
+        }
+    }
+}
+
+/// The parts of the difficulty curve that can be changed one at a time
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Setting {
+    Asteroids,
+    Speed,
+    Size,
+    Mothership,
+    Tolerance,
+    Pods,
+    DockingBonus,
+}
+
+// Percentages move in these steps and stay within these limits
+const STEP: u32 = 25;
+const MIN_PERCENT: u32 = 25;
+const MAX_PERCENT: u32 = 300;
+const TOLERANCE_STEP: u32 = 5;
+const MAX_TOLERANCE: u32 = 50;
+
+/// How hard the game is. Everything is a percentage of the original game,
+/// apart from the tolerance, which is how much of each asteroid blob's
+/// radius the pod can clip without crashing. High scores are kept
+/// separately for each different difficulty.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Difficulty {
+    pub preset: Preset,
+    pub asteroids: u32,
+    pub speed: u32,
+    pub size: u32,
+    pub mothership: u32,
+    pub tolerance: u32,
+    pub pods: u32,
+    pub docking_bonus: bool,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Difficulty::preset(Preset::Normal)
+    }
+}
+
+impl Difficulty {
+    pub fn preset(preset: Preset) -> Difficulty {
+        let (asteroids, speed, size, mothership, tolerance, pods, docking_bonus) = match preset {
+            Preset::Easy => (75, 75, 90, 75, 25, 150, true),
+            Preset::Normal | Preset::Custom => (100, 100, 100, 100, 0, 100, true),
+            Preset::Hard => (125, 125, 110, 125, 0, 75, false),
+        };
+        Difficulty {
+            preset,
+            asteroids,
+            speed,
+            size,
+            mothership,
+            tolerance,
+            pods,
+            docking_bonus,
+        }
+    }
+
+    /// Moves to the next or previous preset. Custom keeps the current settings.
+    pub fn cycle_preset(&mut self, delta: i32) {
+        let idx = Preset::ALL
+            .iter()
+            .position(|p| *p == self.preset)
+            .unwrap_or(0);
+        let preset = Preset::ALL[crate::options::cycle(idx, delta, Preset::ALL.len())];
+        if preset == Preset::Custom {
+            self.preset = preset;
+        } else {
+            *self = Difficulty::preset(preset);
+        }
+    }
+
+    /// The setting's value as shown on the options screen
+    pub fn value(&self, setting: Setting) -> u32 {
+        match setting {
+            Setting::Asteroids => self.asteroids,
+            Setting::Speed => self.speed,
+            Setting::Size => self.size,
+            Setting::Mothership => self.mothership,
+            Setting::Tolerance => self.tolerance,
+            Setting::Pods => self.pods,
+            Setting::DockingBonus => self.docking_bonus as u32,
+        }
+    }
+
+    pub fn change(&mut self, setting: Setting, delta: i32) {
+        let percent = |value: u32| {
+            (value as i32 + delta * STEP as i32).clamp(MIN_PERCENT as i32, MAX_PERCENT as i32)
+                as u32
+        };
+        match setting {
+            Setting::Asteroids => self.asteroids = percent(self.asteroids),
+            Setting::Speed => self.speed = percent(self.speed),
+            Setting::Size => self.size = percent(self.size),
+            Setting::Mothership => self.mothership = percent(self.mothership),
+            Setting::Pods => self.pods = percent(self.pods),
+            Setting::Tolerance => {
+                self.tolerance = (self.tolerance as i32 + delta * TOLERANCE_STEP as i32)
+                    .clamp(0, MAX_TOLERANCE as i32) as u32
+            }
+            Setting::DockingBonus => self.docking_bonus = !self.docking_bonus,
+        }
+        self.preset = Preset::Custom;
+    }
+
+    /// Scales one of the percentage settings
+    pub fn scale(&self, setting: Setting, value: f32) -> f32 {
+        value * self.value(setting) as f32 / 100.0
+    }
+
+    /// How much of a blob's radius counts as a hit
+    pub fn hit_scale(&self) -> f32 {
+        1.0 - self.tolerance as f32 / 100.0
+    }
+
+    pub fn pods(&self, pods: u32) -> u32 {
+        (self.scale(Setting::Pods, pods as f32).round() as u32).max(1)
+    }
+
+    pub fn mothership_speed(&self, speed: i8) -> i8 {
+        let scaled = self.scale(Setting::Mothership, speed.abs() as f32).round();
+        (scaled.clamp(1.0, i8::MAX as f32) as i8) * speed.signum()
+    }
+}
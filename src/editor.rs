@@ -0,0 +1,346 @@
+use std::fs;
+use std::path::Path;
+
+use crate::level::{
+    default_offsets, default_offsets_x, default_radii, AsteroidRow, LevelDefinition,
+    MAX_MOTHERSHIP_SPEED,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor() -> Editor {
+        let mut editor = Editor::new(3, LevelDefinition::default(), 1920.0, 1280.0);
+        editor.level.asteroids.push(AsteroidRow {
+            y: 0.5,
+            x: 0.5,
+            speed: 2.0,
+            radii: [30.0, 40.0, 30.0],
+            offsets: [0.0, 0.0, 0.0],
+            offsets_x: [0.0, 100.0, 200.0],
+        });
+        editor
+    }
+
+    #[test]
+    fn test_hit_test() {
+        let editor = editor();
+        // Blob centres are at (990, 670), (1100, 680) and (1190, 670)
+        assert!(editor.hit_test(990.0, 670.0) == Some(Selection::Blob(0, 0)));
+        assert!(editor.hit_test(1100.0, 700.0) == Some(Selection::Blob(0, 1)));
+        assert!(editor.hit_test(1210.0, 670.0) == Some(Selection::Blob(0, 2)));
+        assert!(editor.hit_test(100.0, 100.0).is_none());
+        // The landing pad sits on the ground in the middle
+        assert!(editor.hit_test(960.0, 1230.0) == Some(Selection::LandingPad));
+        assert!(editor.hit_test(1540.0, 1140.0) == Some(Selection::Moonbase));
+    }
+
+    #[test]
+    fn test_drag_asteroid() {
+        let mut editor = editor();
+        editor.press(990.0, 670.0, false);
+        editor.drag_to(1182.0, 542.0);
+        editor.release();
+        assert!(editor.level.asteroids[0].x == 0.6);
+        assert!(editor.level.asteroids[0].y == 0.4);
+        // Other blobs move relative to the first
+        editor.press(1292.0, 552.0, false);
+        editor.drag_to(1302.0, 562.0);
+        assert!(editor.level.asteroids[0].offsets_x[1] == 110.0);
+        assert!(editor.level.asteroids[0].offsets[1] == 10.0);
+    }
+
+    #[test]
+    fn test_resize_blob() {
+        let mut editor = editor();
+        editor.press(990.0, 670.0, true);
+        editor.drag_to(1040.0, 670.0);
+        assert!(editor.level.asteroids[0].radii[0] == 50.0);
+        editor.drag_to(990.0, 670.0);
+        assert!(editor.level.asteroids[0].radii[0] == MIN_RADIUS);
+    }
+
+    #[test]
+    fn test_add_and_delete() {
+        let mut editor = editor();
+        editor.press(200.0, 300.0, false);
+        editor.release();
+        assert!(editor.level.asteroids.len() == 2);
+        assert!(editor.hit_test(200.0, 300.0) == Some(Selection::Blob(1, 0)));
+        editor.delete_selected();
+        assert!(editor.level.asteroids.len() == 1);
+        assert!(editor.hit_test(200.0, 300.0).is_none());
+    }
+
+    #[test]
+    fn test_scroll_speed_skips_zero() {
+        let mut editor = editor();
+        editor.scroll(990.0, 670.0, -4.0);
+        assert!(editor.level.asteroids[0].speed == 1.0);
+        editor.scroll(990.0, 670.0, -4.0);
+        assert!(editor.level.asteroids[0].speed == -0.25);
+        // Nothing under the mouse and nothing selected
+        editor.scroll(10.0, 10.0, 1.0);
+        assert!(editor.level.asteroids[0].speed == -0.25);
+    }
+
+    #[test]
+    fn test_move_pad_and_moonbase() {
+        let mut editor = editor();
+        editor.press(960.0, 1230.0, false);
+        editor.drag_to(480.0, 1230.0);
+        editor.release();
+        assert!(editor.level.landing_pad.x == 0.25);
+        editor.press(1540.0, 1140.0, false);
+        editor.drag_to(1348.0, 1140.0);
+        assert!(editor.level.moonbase == 0.65);
+    }
+
+    #[test]
+    fn test_mothership() {
+        let mut editor = editor();
+        editor.change_mothership_speed(5);
+        assert!(editor.level.mothership_speed == 15);
+        editor.change_mothership_speed(100);
+        assert!(editor.level.mothership_speed == MAX_MOTHERSHIP_SPEED);
+        editor.reverse_mothership();
+        assert!(editor.level.mothership_speed == -MAX_MOTHERSHIP_SPEED);
+        editor.change_mothership_speed(-100);
+        assert!(editor.level.mothership_speed == -1);
+    }
+}
+
+const MIN_RADIUS: f32 = 5.0;
+const SPEED_STEP: f32 = 0.25;
+
+/// Something in the level that can be picked up with the mouse
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Selection {
+    /// Asteroid index, blob index
+    Blob(usize, usize),
+    LandingPad,
+    Moonbase,
+}
+
+enum Drag {
+    // Where the mouse grabbed, relative to the thing being moved
+    Move(f32, f32),
+    Resize,
+}
+
+fn round(value: f32) -> f32 {
+    (value * 1000.0).round() / 1000.0
+}
+
+/// Edits a level with the mouse. Works in window coordinates and converts
+/// to the level file's units, which are fractions of the screen for
+/// positions and pixels at 1920 wide for sizes.
+pub struct Editor {
+    pub number: u32,
+    pub level: LevelDefinition,
+    pub selected: Option<Selection>,
+    /// The result of the last save
+    pub message: String,
+    drag: Option<Drag>,
+    width: f32,
+    height: f32,
+    scale: f32,
+}
+
+impl Editor {
+    pub fn new(number: u32, level: LevelDefinition, width: f32, height: f32) -> Editor {
+        Editor {
+            number,
+            level,
+            selected: None,
+            message: String::new(),
+            drag: None,
+            width,
+            height,
+            scale: width / 1920.0,
+        }
+    }
+
+    // Centre and radius of a blob on screen
+    fn blob(&self, asteroid: usize, blob: usize) -> (f32, f32, f32) {
+        let row = &self.level.asteroids[asteroid];
+        let radius = row.radii[blob] * self.scale;
+        (
+            row.x * self.width + row.offsets_x[blob] * self.scale + radius,
+            row.y * self.height + row.offsets[blob] * self.scale + radius,
+            radius,
+        )
+    }
+
+    // Top left of the landing pad, and its size, as Game draws it
+    fn landing_pad(&self) -> (f32, f32, f32, f32) {
+        let width = self.level.landing_pad.width * self.scale;
+        let height = 20.0 * self.scale;
+        (
+            self.level.landing_pad.x * self.width - width / 2.0,
+            self.height - 40.0 * self.scale - height,
+            width,
+            height,
+        )
+    }
+
+    // Centre and radius of the moonbase, as Game draws it
+    fn moonbase(&self) -> (f32, f32, f32) {
+        let radius = 100.0 * self.scale;
+        (
+            self.level.moonbase * self.width + radius,
+            self.height - 40.0 * self.scale,
+            radius,
+        )
+    }
+
+    /// What is under the mouse, with the topmost blob first
+    pub fn hit_test(&self, x: f32, y: f32) -> Option<Selection> {
+        for asteroid in (0..self.level.asteroids.len()).rev() {
+            // Game draws blob 1 on top of blob 2 on top of blob 3
+            for blob in 0..3 {
+                let (cx, cy, radius) = self.blob(asteroid, blob);
+                if (x - cx).powi(2) + (y - cy).powi(2) <= radius.powi(2) {
+                    return Some(Selection::Blob(asteroid, blob));
+                }
+            }
+        }
+        let (pad_x, pad_y, pad_width, pad_height) = self.landing_pad();
+        if x >= pad_x && x <= pad_x + pad_width && y >= pad_y && y <= pad_y + pad_height {
+            return Some(Selection::LandingPad);
+        }
+        let (cx, cy, radius) = self.moonbase();
+        if y <= cy && (x - cx).powi(2) + (y - cy).powi(2) <= radius.powi(2) {
+            return Some(Selection::Moonbase);
+        }
+        None
+    }
+
+    /// Picks up whatever is under the mouse to move it, or to resize it if
+    /// it's a blob. Clicking on empty space adds a new asteroid there.
+    pub fn press(&mut self, x: f32, y: f32, resize: bool) {
+        self.selected = self.hit_test(x, y);
+        if self.selected.is_none() && !resize {
+            let radius = default_radii()[0] * self.scale;
+            self.level.asteroids.push(AsteroidRow {
+                y: round(((y - radius) / self.height).clamp(0.0, 1.0)),
+                x: round(((x - radius) / self.width).clamp(0.0, 1.0)),
+                speed: 2.0,
+                radii: default_radii(),
+                offsets: [0.0; 3],
+                offsets_x: [0.0; 3],
+            });
+            let row = self.level.asteroids.last_mut().unwrap();
+            // Keep the usual shape, relative to the first blob
+            for blob in 1..3 {
+                row.offsets[blob] = default_offsets()[blob] - default_offsets()[0];
+                row.offsets_x[blob] = default_offsets_x()[blob];
+            }
+            self.selected = Some(Selection::Blob(self.level.asteroids.len() - 1, 0));
+        }
+        self.drag = match self.selected {
+            None => None,
+            Some(Selection::Blob(..)) if resize => Some(Drag::Resize),
+            Some(Selection::Blob(asteroid, 0)) => {
+                let row = &self.level.asteroids[asteroid];
+                Some(Drag::Move(x - row.x * self.width, y - row.y * self.height))
+            }
+            Some(Selection::Blob(asteroid, blob)) => {
+                let row = &self.level.asteroids[asteroid];
+                Some(Drag::Move(
+                    x - row.offsets_x[blob] * self.scale,
+                    y - row.offsets[blob] * self.scale,
+                ))
+            }
+            Some(Selection::LandingPad) => {
+                Some(Drag::Move(x - self.level.landing_pad.x * self.width, 0.0))
+            }
+            Some(Selection::Moonbase) => {
+                Some(Drag::Move(x - self.level.moonbase * self.width, 0.0))
+            }
+        };
+    }
+
+    pub fn drag_to(&mut self, x: f32, y: f32) {
+        let (Some(drag), Some(selected)) = (&self.drag, self.selected) else {
+            return;
+        };
+        match (drag, selected) {
+            (Drag::Resize, Selection::Blob(asteroid, blob)) => {
+                let (cx, cy, _) = self.blob(asteroid, blob);
+                let radius = ((x - cx).powi(2) + (y - cy).powi(2)).sqrt() / self.scale;
+                let row = &mut self.level.asteroids[asteroid];
+                // Grow around the centre rather than the top left
+                let change = radius.max(MIN_RADIUS).round() - row.radii[blob];
+                row.radii[blob] += change;
+                row.offsets_x[blob] -= change;
+                row.offsets[blob] -= change;
+            }
+            (Drag::Move(grab_x, grab_y), Selection::Blob(asteroid, 0)) => {
+                let row = &mut self.level.asteroids[asteroid];
+                row.x = round(((x - grab_x) / self.width).clamp(0.0, 1.0));
+                row.y = round(((y - grab_y) / self.height).clamp(0.0, 1.0));
+            }
+            (Drag::Move(grab_x, grab_y), Selection::Blob(asteroid, blob)) => {
+                let row = &mut self.level.asteroids[asteroid];
+                row.offsets_x[blob] = ((x - grab_x) / self.scale).round();
+                row.offsets[blob] = ((y - grab_y) / self.scale).round();
+            }
+            (Drag::Move(grab_x, _), Selection::LandingPad) => {
+                self.level.landing_pad.x = round(((x - grab_x) / self.width).clamp(0.0, 1.0));
+            }
+            (Drag::Move(grab_x, _), Selection::Moonbase) => {
+                self.level.moonbase = round(((x - grab_x) / self.width).clamp(0.0, 1.0));
+            }
+            _ => {}
+        }
+    }
+
+    pub fn release(&mut self) {
+        self.drag = None;
+    }
+
+    /// Changes the speed of the asteroid under the mouse, or the selected
+    /// one. Asteroids can't stand still so the speed jumps over zero.
+    pub fn scroll(&mut self, x: f32, y: f32, delta: f32) {
+        let asteroid = match self.hit_test(x, y).or(self.selected) {
+            Some(Selection::Blob(asteroid, _)) => asteroid,
+            _ => return,
+        };
+        let row = &mut self.level.asteroids[asteroid];
+        let mut speed = row.speed + delta * SPEED_STEP;
+        if speed == 0.0 || speed.signum() != row.speed.signum() {
+            speed = SPEED_STEP * delta.signum();
+        }
+        row.speed = speed;
+    }
+
+    pub fn delete_selected(&mut self) {
+        if let Some(Selection::Blob(asteroid, _)) = self.selected {
+            self.level.asteroids.remove(asteroid);
+            self.selected = None;
+            self.drag = None;
+        }
+    }
+
+    /// Changes how fast the mothership moves, keeping its direction
+    pub fn change_mothership_speed(&mut self, delta: i8) {
+        let speed = &mut self.level.mothership_speed;
+        let direction = speed.signum();
+        *speed = direction * (speed.abs().saturating_add(delta)).clamp(1, MAX_MOTHERSHIP_SPEED);
+    }
+
+    pub fn reverse_mothership(&mut self) {
+        self.level.mothership_speed = -self.level.mothership_speed;
+    }
+
+    /// Checks the level and writes it to `level_<n>.toml` in the directory
+    pub fn save(&self, dir: &Path) -> Result<(), String> {
+        let contents = self.level.to_toml()?;
+        LevelDefinition::from_toml(&contents)?;
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        fs::write(dir.join(format!("level_{}.toml", self.number)), contents)
+            .map_err(|e| e.to_string())
+    }
+}
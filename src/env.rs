@@ -0,0 +1,297 @@
+use std::io::{BufRead, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::events::EventKind;
+use crate::game::{Game, GameStatus, PodStatus};
+use crate::mode::GameMode;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env() -> Env {
+        Env::new(Game::new(1920, 1280, "res".to_string()), GameMode::Endless)
+    }
+
+    #[test]
+    fn test_reset_is_repeatable() {
+        let mut env = env();
+        let first = env.reset(7);
+        assert!(first.game_status == GameStatus::Playing);
+        assert!(first.pod_status == PodStatus::Inactive);
+        assert!(first.asteroids.len() == NEAREST_ASTEROIDS);
+        assert!(env.step(Action::default()).observation != first);
+        assert!(env.reset(7) == first);
+        assert!(env.reset(8) != first);
+    }
+
+    #[test]
+    fn test_step() {
+        let mut env = env();
+        env.reset(1);
+        let result = env.step(Action {
+            steer: 9,
+            drop: true,
+            launch: false,
+        });
+        assert!(result.observation.tick == 1);
+        assert!(result.observation.pod_status == PodStatus::Dropping);
+        assert!(result.observation.pod_delta == 4);
+        assert!(!result.done);
+        // Nearest first
+        let distances: Vec<f32> = result
+            .observation
+            .asteroids
+            .iter()
+            .map(|a| a.distance)
+            .collect();
+        assert!(distances.windows(2).all(|pair| pair[0] <= pair[1]));
+        // Keep steering right, to the edge of the screen and into the ground,
+        // until the pods run out
+        let mut result = result;
+        let mut lost = 0.0;
+        while !result.done {
+            result = env.step(Action {
+                steer: 4,
+                drop: true,
+                launch: false,
+            });
+            lost += result.reward;
+        }
+        assert!(result.observation.game_status == GameStatus::GameOver);
+        assert!(lost < 0.0);
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let mut env = env();
+        let reply = env.respond(r#"{"reset": 3}"#);
+        assert!(reply.starts_with(r#"{"observation":{"tick":0,"game_status":"Playing""#));
+        let reply = env.respond(r#"{"step": {"steer": -2, "drop": true}}"#);
+        assert!(reply.contains(r#""pod_status":"Dropping""#));
+        assert!(reply.ends_with(r#""reward":0.0,"done":false}"#));
+        assert!(env.respond("{\"jump\": 1}").starts_with(r#"{"error":"#));
+    }
+}
+
+// How many asteroids are described in each observation, nearest first
+pub const NEAREST_ASTEROIDS: usize = 8;
+// Taken off the reward for each pod lost
+const CRASH_PENALTY: f32 = 100.0;
+
+/// What the agent does in one frame, using the same controls as a player
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(default)]
+pub struct Action {
+    /// Pixels a frame to steer the pod, from -4 (left) to 4 (right)
+    pub steer: i8,
+    pub drop: bool,
+    pub launch: bool,
+}
+
+/// An asteroid as the agent sees it
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AsteroidObservation {
+    /// The centre and radius of each of its blobs
+    pub blobs: [[f32; 3]; 3],
+    /// Pixels moved across each frame, negative to the left
+    pub speed: f32,
+    /// From the middle of the pod to the nearest edge of a blob
+    pub distance: f32,
+}
+
+/// Everything the agent can see after a frame. Positions are in window
+/// pixels from the top left, and the pod's is its top left corner.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Observation {
+    /// Frames since the last reset
+    pub tick: u32,
+    pub game_status: GameStatus,
+    pub pod_status: PodStatus,
+    pub level: u32,
+    pub score: u32,
+    pub pods_remaining: u32,
+    pub men_to_rescue: u32,
+    pub pod_x: f32,
+    pub pod_y: f32,
+    pub pod_delta: i8,
+    pub pod_size: f32,
+    pub mothership_x: f32,
+    pub mothership_y: f32,
+    /// Pixels moved across each frame, negative to the left
+    pub mothership_speed: f32,
+    pub mothership_width: f32,
+    pub landing_pad_x: f32,
+    pub landing_pad_width: f32,
+    /// How far down the pod is once it has landed
+    pub landing_y: f32,
+    pub asteroids: Vec<AsteroidObservation>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StepResult {
+    pub observation: Observation,
+    /// Points scored this frame, less a penalty for each pod lost
+    pub reward: f32,
+    /// The game is over
+    pub done: bool,
+}
+
+// One line of the JSON-lines protocol, like {"reset": 42} or
+// {"step": {"steer": -4, "drop": true}}
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Request {
+    Reset(u64),
+    Step(Action),
+}
+
+/// The game as an environment for training agents, with no window. Each
+/// step is one frame.
+pub struct Env {
+    game: Game,
+    mode: GameMode,
+    tick: u32,
+}
+
+impl Env {
+    pub fn new(game: Game, mode: GameMode) -> Env {
+        Env {
+            game,
+            mode,
+            tick: 0,
+        }
+    }
+
+    /// Starts a new game, with levels made from `seed`
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.game.set_mode(self.mode);
+        self.game.set_seed(seed);
+        self.game.restart();
+        self.game.play();
+        self.game.sounds_to_play.clear();
+        self.game.status_changes.clear();
+        self.game.events.clear();
+        self.tick = 0;
+        self.observe()
+    }
+
+    pub fn step(&mut self, action: Action) -> StepResult {
+        // Levels follow on from each other without waiting for Enter
+        if self.game.game_status() == GameStatus::NewLevel {
+            self.game.start();
+        }
+        let score = self.game.score();
+        self.game.pod_set_delta(action.steer);
+        if action.drop {
+            self.game.drop_pod();
+        }
+        if action.launch {
+            self.game.launch_pod();
+        }
+        if self.game.get_pod_delta() != 0 {
+            self.game.pod_manoeuvre();
+        }
+        self.game.next_frame();
+        let crashes = self
+            .game
+            .events
+            .iter()
+            .filter(|event| matches!(event.kind, EventKind::PodDestroyed { .. }))
+            .count();
+        self.game.sounds_to_play.clear();
+        self.game.status_changes.clear();
+        self.game.events.clear();
+        self.tick += 1;
+        StepResult {
+            observation: self.observe(),
+            reward: self.game.score().saturating_sub(score) as f32 - CRASH_PENALTY * crashes as f32,
+            done: self.game.game_status() == GameStatus::GameOver,
+        }
+    }
+
+    fn observe(&self) -> Observation {
+        let field = self.game.field();
+        let (pod_x, pod_y) = self.game.pod_position();
+        let (centre_x, centre_y) = (pod_x + field.pod_size / 2.0, pod_y + field.pod_size / 2.0);
+        let mut asteroids: Vec<AsteroidObservation> = field
+            .rocks
+            .iter()
+            .map(|rock| {
+                let blobs = rock
+                    .blobs
+                    .map(|(dx, dy, radius)| [rock.x + dx + radius, rock.y + dy + radius, radius]);
+                let distance = blobs
+                    .iter()
+                    .map(|[x, y, radius]| {
+                        ((x - centre_x).powi(2) + (y - centre_y).powi(2)).sqrt() - radius
+                    })
+                    .fold(f32::MAX, f32::min);
+                AsteroidObservation {
+                    blobs,
+                    speed: rock.speed,
+                    distance,
+                }
+            })
+            .collect();
+        asteroids.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        asteroids.truncate(NEAREST_ASTEROIDS);
+        Observation {
+            tick: self.tick,
+            game_status: self.game.game_status(),
+            pod_status: self.game.pod_status(),
+            level: self.game.level(),
+            score: self.game.score(),
+            pods_remaining: self.game.pods_remaining(),
+            men_to_rescue: self.game.men_to_rescue(),
+            pod_x,
+            pod_y,
+            pod_delta: self.game.get_pod_delta(),
+            pod_size: field.pod_size,
+            mothership_x: field.mothership_x,
+            mothership_y: field.mothership_y,
+            mothership_speed: field.mothership_direction as f32 * field.size_multiplier,
+            mothership_width: field.mothership_width,
+            landing_pad_x: field.landing_pad_x,
+            landing_pad_width: field.landing_pad_width,
+            landing_y: field.landing_y,
+            asteroids,
+        }
+    }
+
+    /// Answers one line of the JSON-lines protocol with another
+    pub fn respond(&mut self, line: &str) -> String {
+        let result = match serde_json::from_str(line) {
+            Ok(Request::Reset(seed)) => Ok(StepResult {
+                observation: self.reset(seed),
+                reward: 0.0,
+                done: false,
+            }),
+            Ok(Request::Step(action)) => Ok(self.step(action)),
+            Err(e) => Err(e.to_string()),
+        };
+        match result {
+            Ok(result) => serde_json::to_string(&result).unwrap_or_else(|e| error(&e.to_string())),
+            Err(e) => error(&e),
+        }
+    }
+
+    /// Reads requests a line at a time and writes a line back for each, so
+    /// another process can play through a pipe
+    pub fn serve(&mut self, input: impl BufRead, mut output: impl Write) -> Result<(), String> {
+        for line in input.lines() {
+            let line = line.map_err(|e| e.to_string())?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            writeln!(output, "{}", self.respond(&line)).map_err(|e| e.to_string())?;
+            output.flush().map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+fn error(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
@@ -0,0 +1,179 @@
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::achievements::Achievement;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct Counter(Rc<RefCell<u32>>);
+
+    impl Subscriber for Counter {
+        fn notify(&mut self, _event: &Event) {
+            *self.0.borrow_mut() += 1;
+        }
+    }
+
+    #[test]
+    fn test_everyone_hears_everything() {
+        let heard = Rc::new(RefCell::new(0));
+        let mut bus = EventBus::default();
+        bus.subscribe(Box::new(Counter(heard.clone())));
+        bus.subscribe(Box::new(Counter(heard.clone())));
+        let event = Event {
+            tick: 3,
+            x: 10.0,
+            y: 20.0,
+            kind: EventKind::Landed,
+        };
+        bus.publish(&[event, event]);
+        assert!(*heard.borrow() == 4);
+    }
+
+    #[test]
+    fn test_log() {
+        let mut log = EventLog::new(Vec::new());
+        log.notify(&Event {
+            tick: 7,
+            x: 1.0,
+            y: 2.0,
+            kind: EventKind::PodDestroyed {
+                cause: Cause::Ground,
+                ascending: false,
+                from_mothership: 0.5,
+            },
+        });
+        assert!(
+            String::from_utf8(log.output).unwrap()
+                == "{\"tick\":7,\"x\":1.0,\"y\":2.0,\"kind\":\
+                    {\"PodDestroyed\":{\"cause\":\"Ground\",\"ascending\":false,\
+                    \"from_mothership\":0.5}}}\n"
+        );
+    }
+}
+
+/// What blew a pod up
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+pub enum Cause {
+    Asteroid,
+    /// Missed the landing pad
+    Ground,
+    /// Missed the mothership, with no autodock to help
+    Mothership,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum EventKind {
+    /// A game started, or a practice level started again. Practice, playtests
+    /// and the autopilot's games aren't on the player's record.
+    GameStarted {
+        level: u32,
+        on_record: bool,
+    },
+    PodDropped,
+    /// The pod started scraping past an asteroid. Closeness goes from 0.0
+    /// (barely touched the margin) to 1.0.
+    NearMiss {
+        closeness: f32,
+    },
+    PodDestroyed {
+        cause: Cause,
+        ascending: bool,
+        /// How far the pod was from the mothership, as a share of the
+        /// window's height
+        from_mothership: f32,
+    },
+    Landed,
+    /// The terran got into the pod
+    RescueeBoarded,
+    /// The pod took off from the landing pad
+    Launched,
+    Docked {
+        autodock: bool,
+    },
+    /// Extra pods or points for docking without help or beating par
+    BonusAwarded {
+        pods: u32,
+        points: u32,
+    },
+    LevelComplete {
+        level: u32,
+        /// Whether the level's rules carry unused pods over
+        carry_over_pods: bool,
+    },
+    GameOver {
+        level: u32,
+        score: u32,
+    },
+    /// The player left before the game was over, for the title screen or by
+    /// closing the window
+    GameQuit {
+        level: u32,
+    },
+    AchievementUnlocked {
+        achievement: Achievement,
+    },
+}
+
+/// Something that happened in a game, and where. Positions are of the middle
+/// of the pod, in window pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Event {
+    /// Frames played since the game started
+    pub tick: u32,
+    pub x: f32,
+    pub y: f32,
+    pub kind: EventKind,
+}
+
+/// Something that wants to hear about what happens in the game
+pub trait Subscriber {
+    fn notify(&mut self, event: &Event);
+}
+
+/// Hands every event on to everything that has subscribed, so the game
+/// doesn't need to know who is listening
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Vec<Box<dyn Subscriber>>,
+}
+
+impl EventBus {
+    pub fn subscribe(&mut self, subscriber: Box<dyn Subscriber>) {
+        self.subscribers.push(subscriber);
+    }
+
+    pub fn publish(&mut self, events: &[Event]) {
+        for event in events {
+            for subscriber in self.subscribers.iter_mut() {
+                subscriber.notify(event);
+            }
+        }
+    }
+}
+
+/// Writes each event out as a line of JSON
+pub struct EventLog<W: Write> {
+    output: W,
+}
+
+impl<W: Write> EventLog<W> {
+    pub fn new(output: W) -> EventLog<W> {
+        EventLog { output }
+    }
+}
+
+impl<W: Write> Subscriber for EventLog<W> {
+    fn notify(&mut self, event: &Event) {
+        let written = serde_json::to_string(event)
+            .map_err(|e| e.to_string())
+            .and_then(|line| writeln!(self.output, "{}", line).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            eprintln!("Couldn't log event: {}", e);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::game::Game;
+use crate::mode::GameMode;
+use crate::remote;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameStatus;
+
+    #[test]
+    fn test_random_play() {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        for seed in 0..40 {
+            let inputs = random_inputs(seed, 2000);
+            if let Err(e) = replay(&mut game, &inputs) {
+                panic!("random inputs from seed {}: {}", seed, e);
+            }
+        }
+    }
+
+    #[test]
+    fn test_short_and_empty_inputs() {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        assert!(replay(&mut game, &[]).is_ok());
+        assert!(replay(&mut game, &[3, 200]).is_ok());
+        // Confirm, then hold drop and steer hard left into the ground
+        let mut inputs = vec![0; HEADER_BYTES];
+        inputs.push(Input::CONFIRM);
+        inputs.extend([Input::DROP; 400]);
+        assert!(replay(&mut game, &inputs).is_ok());
+        assert!(game.game_status() == GameStatus::GameOver);
+        assert!(game.pods_remaining() == 0);
+    }
+}
+
+// Bytes at the start of an input stream that say how the game is set up
+const HEADER_BYTES: usize = 10;
+// The levels a stream can start on
+const LEVELS: u8 = 12;
+
+/// One frame of input packed into a byte, so that fuzzers can make them up:
+/// the low four bits steer, and the high four drop, launch, pause and confirm
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Input(u8);
+
+impl Input {
+    const DROP: u8 = 0x10;
+    const LAUNCH: u8 = 0x20;
+    const PAUSE: u8 = 0x40;
+    const CONFIRM: u8 = 0x80;
+
+    fn steer(self) -> i8 {
+        (self.0 & 0x0f) as i8 % 9 - 4
+    }
+
+    /// The same, without the keys that are only ever tapped
+    fn held(self) -> Input {
+        Input(self.0 & !(Input::PAUSE | Input::CONFIRM))
+    }
+
+    fn press(self, game: &mut Game) {
+        game.pod_set_delta(self.steer());
+        if self.0 & Input::DROP != 0 {
+            game.drop_pod();
+        }
+        if self.0 & Input::LAUNCH != 0 {
+            game.launch_pod();
+        }
+        if self.0 & Input::PAUSE != 0 {
+            remote::execute(game, "pause");
+        }
+        if self.0 & Input::CONFIRM != 0 {
+            remote::execute(game, "confirm");
+        }
+    }
+}
+
+/// Plays `bytes` through as one game, checking that the game still makes
+/// sense after every frame. The first byte picks the mode, the next the level
+/// and the next eight the seed. Each byte after that is a frame of input: the
+/// low four bits steer, and the high four drop, launch, pause and confirm.
+pub fn replay(game: &mut Game, bytes: &[u8]) -> Result<(), String> {
+    let mut header = [0; HEADER_BYTES];
+    let (start, inputs) = bytes.split_at(bytes.len().min(HEADER_BYTES));
+    header[..start.len()].copy_from_slice(start);
+    let mode = GameMode::ALL[header[0] as usize % GameMode::ALL.len()];
+    let level = 1 + (header[1] % LEVELS) as u32;
+    let seed = u64::from_le_bytes(header[2..].try_into().unwrap_or_default());
+    game.stop_attract();
+    game.set_mode(mode);
+    game.set_seed(seed);
+    game.restart();
+    game.play_level(level, None);
+    game.check_invariants()
+        .map_err(|e| format!("{} at the start", e))?;
+    for (frame, input) in inputs.iter().enumerate() {
+        Input(*input).press(game);
+        if game.get_pod_delta() != 0 {
+            game.pod_manoeuvre();
+        }
+        game.next_frame();
+        game.sounds_to_play.clear();
+        game.status_changes.clear();
+        game.events.clear();
+        game.check_invariants().map_err(|e| {
+            format!(
+                "{} after frame {} of {:?} on level {}",
+                e,
+                frame,
+                mode,
+                game.level()
+            )
+        })?;
+    }
+    Ok(())
+}
+
+/// Inputs for `replay` made up from `seed`, holding each for a while the way a
+/// player would rather than changing every frame
+pub fn random_inputs(seed: u64, frames: usize) -> Vec<u8> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut inputs: Vec<u8> = (0..HEADER_BYTES).map(|_| rng.gen()).collect();
+    while inputs.len() < HEADER_BYTES + frames {
+        // Pausing and confirming now and then, rather than every other time
+        let input = match rng.gen_range(0..20) {
+            0 => Input(rng.gen()),
+            _ => Input(rng.gen()).held(),
+        };
+        inputs.push(input.0);
+        for _ in 0..rng.gen_range(0..30) {
+            inputs.push(input.held().0);
+        }
+    }
+    inputs.truncate(HEADER_BYTES + frames);
+    inputs
+}
@@ -1,16 +1,47 @@
+use std::cell::RefCell;
 use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::rc::Rc;
 
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 use sfml::cpp::FBox;
 use sfml::graphics::{
-    CircleShape, Color, Font, RectangleShape, RenderTarget, RenderWindow, Shape, Text,
-    Transformable,
+    CircleShape, Color, Font, RectangleShape, RenderTarget, Shape, Text, Transformable,
 };
 use sfml::system::Vector2f;
 
+use crate::achievements::Achievement;
+use crate::attract::{self, Stage};
+use crate::audio::{self, AudioSettings};
+use crate::bot::Bot;
+use crate::daily::{self, DailyResult};
+use crate::difficulty::{Difficulty, Setting};
+use crate::editor::{Editor, Selection};
+use crate::events::{Cause, Event, EventKind};
+use crate::highscores::{Entry, HighScores, Run};
+use crate::level::{LevelDefinition, Rules};
+use crate::mode::{self, GameMode, Hazard};
+use crate::music::MusicContext;
+use crate::options::{self, OptionItem};
+use crate::profile::{Profiles, Stars};
+use crate::scenario::Setup;
+use crate::solver::{self, Field, Rock};
+use crate::state::{self, StatusChange, Transition};
+use crate::theme::Theme;
+use crate::timeattack::{self, LevelTime, FRAMES_PER_SECOND};
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::achievements::AchievementsRecorder;
+    use crate::audio::SoundCues;
+    use crate::difficulty::Preset;
+    use crate::events::{EventBus, Subscriber};
+    use crate::stats::StatsRecorder;
+    use std::sync::mpsc;
 
     #[test]
     fn test_distance() {
@@ -68,10 +99,580 @@ mod tests {
         };
         game.asteroids.push(asteroid);
         assert!(game.check_for_pod_collision() == CollisionType::None);
-        assert!(game.asteroids.len() > 0);
+        assert!(!game.asteroids.is_empty());
+    }
+
+    #[test]
+    fn test_level_from_file() {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        let definition = LevelDefinition::from_toml(
+            "men_to_rescue = 3\npods = 2\n\
+             [landing_pad]\nx = 0.25\nwidth = 200\n\
+             [rules]\ncarry_over_pods = false\n\
+             [[asteroid]]\ny = 0.5\nx = 0.5\nspeed = -2.0\n",
+        )
+        .unwrap();
+        game.levels.insert(5, definition);
+        game.pods_remaining = 4;
+        game.new_level(5);
+        assert!(game.men_to_rescue == 3);
+        assert!(game.pods_remaining == 2);
+        assert!(game.asteroids.len() == 1);
+        assert!(game.asteroids[0].x_pos == 960.0 && game.asteroids[0].y_pos == 640.0);
+        assert!(game.landing_pad_x == 380.0);
+        // No file for level 6, so it's made up
+        game.new_level(6);
+        assert!(game.asteroids.len() == 28);
+        assert!(game.pods_remaining > 2);
+    }
+
+    #[test]
+    fn test_playtests_are_off_the_record() {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        // As editor_play does, without saving the level first
+        game.playtesting = true;
+        game.new_game(7);
+        game.transition(Transition::Start);
+        game.level_complete();
+        assert!(
+            game.events[0].kind
+                == EventKind::GameStarted {
+                    level: 7,
+                    on_record: false
+                }
+        );
+        let profiles = game.profiles.borrow();
+        assert!(profiles.current().unlocked_levels() == 1);
+        assert!(profiles.current().stars(7).count() == 0);
+        drop(profiles);
+        game.restart();
+        assert!(!game.playtesting);
+    }
+
+    #[test]
+    fn test_autopilot_is_off_the_record() {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        game.set_autopiloted(true);
+        game.new_game(4);
+        game.play();
+        game.score = 1000;
+        game.game_over();
+        assert!(game.profiles.borrow().current().unlocked_levels() == 1);
+        assert!(game.high_score_rank.is_none() && game.high_scores.entries.is_empty());
+    }
+
+    #[test]
+    fn test_editing_lays_out_the_level() {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        game.open_editor();
+        assert!(game.mothership_direction == 10 && game.mothership_pos_x == 50.0);
+        game.edit(|editor| editor.reverse_mothership());
+        assert!(game.mothership_direction == -10);
+    }
+
+    #[test]
+    fn test_difficulty() {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        game.new_game(6);
+        let normal_pods = game.pods_remaining;
+        assert!(game.asteroids.len() == 28);
+        game.chosen_difficulty = Difficulty::preset(Preset::Easy);
+        game.new_game(6);
+        assert!(game.pods_remaining > normal_pods);
+        assert!(game.asteroids.len() == 21);
+        assert!(game.mothership_direction.abs() < 10);
+        // Scores are kept apart for each difficulty
+        game.game_status = GameStatus::Playing;
+        game.score = 500;
+        game.game_over();
+        assert!(game.high_score_rank == Some(0));
+        assert!(game
+            .high_scores
+            .top(GameMode::Classic, &Difficulty::default())
+            .is_empty());
+    }
+
+    #[test]
+    fn test_endless_levels() {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        game.mode = GameMode::Endless;
+        game.new_game(1);
+        // Endless levels are all made up, even where there's a file
+        let named = LevelDefinition {
+            name: "Hand-made".to_string(),
+            ..LevelDefinition::default()
+        };
+        game.levels.insert(1, named);
+        game.new_level(1);
+        assert!(game.level_name.is_empty());
+        game.new_level(u32::MAX);
+        assert!(game.men_to_rescue == 5);
+        assert!(game.asteroids.len() >= mode::MAX_ASTEROIDS as usize);
+        // There's no level after the last, so it goes round again
+        game.game_status = GameStatus::Playing;
+        game.level_complete();
+        assert!(game.level == u32::MAX && game.game_status == GameStatus::NewLevel);
+    }
+
+    #[test]
+    fn test_seeded_levels_are_the_same_for_everyone() {
+        let level_2 = |mothership_x: f32, direction: i8| {
+            let mut game = Game::new(1920, 1280, "res".to_string());
+            game.mode = GameMode::Daily;
+            game.new_game(1);
+            game.game_status = GameStatus::Playing;
+            game.mothership_pos_x = mothership_x;
+            game.mothership_direction = direction;
+            game.level_complete();
+            let field: Vec<(f32, f32, f32)> = game
+                .asteroids
+                .iter()
+                .map(|a| (a.x_pos, a.y_pos, a.speed))
+                .collect();
+            (field, game.mothership_pos_x, game.mothership_direction)
+        };
+        assert!(level_2(100.0, 10) == level_2(1500.0, -10));
+    }
+
+    #[test]
+    fn test_time_attack() {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        game.mode = GameMode::TimeAttack;
+        game.new_game(1);
+        game.game_status = GameStatus::Playing;
+        assert!(game.level_par == timeattack::par_frames(game.men_to_rescue));
+        // A quick level earns a pod on top of the usual ones
+        game.level_frames = game.level_par / 2;
+        let pods = game.pods_remaining;
+        game.level_complete();
+        assert!(game.level_times.len() == 1 && game.level_times[0].under_par());
+        assert!(game.pods_remaining > pods);
+        assert!(game.level == 2 && game.level_frames == 0);
+        // Dawdling loses the run
+        game.game_status = GameStatus::Playing;
+        for _ in 0..timeattack::limit_frames(game.level_par) {
+            game.next_frame();
+        }
+        assert!(game.out_of_time);
+        assert!(game.game_status == GameStatus::GameOver);
+        assert!(game.best_run_rank.is_none());
+    }
+
+    #[test]
+    fn test_daily_run() {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        game.chosen_difficulty = Difficulty::preset(Preset::Hard);
+        game.mode = GameMode::Daily;
+        game.today = 20_000;
+        game.new_game(1);
+        // Everyone gets the same made-up levels on the same settings
+        assert!(game.difficulty == Difficulty::default());
+        assert!(game.level_name.is_empty());
+        let positions: Vec<f32> = game.asteroids.iter().map(|a| a.x_pos).collect();
+        game.new_game(1);
+        assert!(game.asteroids.iter().map(|a| a.x_pos).collect::<Vec<_>>() == positions);
+        // Only the first go counts, from the moment it starts
+        assert!(game.daily_scored);
+        game.play();
+        assert!(game.high_scores.daily_result(20_000).unwrap().score == 0);
+        game.score = 700;
+        game.game_over();
+        assert!(game.high_scores.daily_result(20_000).unwrap().score == 700);
+        game.new_game(1);
+        assert!(!game.daily_scored);
+        game.game_status = GameStatus::Playing;
+        game.score = 900;
+        game.game_over();
+        assert!(game.high_scores.daily_result(20_000).unwrap().score == 700);
+        assert!(game.high_scores.entries.is_empty());
+    }
+
+    #[test]
+    fn test_practice() {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        game.profiles.borrow_mut().current_mut().reach(3);
+        game.mode = GameMode::Practice;
+        game.start();
+        assert!(game.game_status == GameStatus::LevelSelect);
+        game.select_level(5);
+        assert!(game.selected_level == 3);
+        game.toggle_freeze();
+        game.start();
+        assert!(game.game_status == GameStatus::Playing && game.level == 3);
+        // Frozen asteroids stay put and pods never run out
+        let positions: Vec<f32> = game.asteroids.iter().map(|a| a.x_pos).collect();
+        game.next_frame();
+        assert!(game.asteroids.iter().map(|a| a.x_pos).collect::<Vec<_>>() == positions);
+        let pods = game.pods_remaining;
+        game.pod_status = PodStatus::Dropping;
+        game.explode_pod(Cause::Ground);
+        assert!(game.pods_remaining == pods);
+        // Nothing gets into the high scores, and practising unlocks nothing
+        game.score = 1000;
+        game.game_over();
+        assert!(game.high_scores.entries.is_empty());
+        game.restart_level();
+        game.new_level(4);
+        assert!(game.profiles.borrow().current().unlocked_levels() == 3);
+    }
+
+    #[test]
+    fn test_continue_and_stars() {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        // With nothing unlocked there's nothing to choose
+        game.start();
+        assert!(game.game_status == GameStatus::Playing);
+        // Level 1 done without crashes or autodock, but slowly
+        game.level_frames = game.level_par;
+        game.level_complete();
+        assert!(game.level == 2);
+        let stars = game.profiles.borrow().current().stars(1);
+        assert!(stars.no_crashes && stars.no_autodock && !stars.under_par);
+        game.start();
+        game.pod_status = PodStatus::Dropping;
+        game.explode_pod(Cause::Ground);
+        game.level_complete();
+        assert!(!game.profiles.borrow().current().stars(2).no_crashes);
+        // Next time, carry on from the furthest level with a standard allowance
+        game.restart();
+        game.start();
+        assert!(game.game_status == GameStatus::LevelSelect);
+        assert!(game.selected_level == 3);
+        game.start();
+        assert!(game.game_status == GameStatus::Playing && game.level == 3);
+        assert!(game.pods_remaining >= CONTINUE_PODS);
+        // Somebody else starts from scratch
+        game.select_profile("Bob");
+        game.restart();
+        game.start();
+        assert!(game.game_status == GameStatus::Playing && game.level == 1);
+    }
+
+    #[test]
+    fn test_status_changes() {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        // Losing focus on the title screen doesn't leave it paused, ready
+        // for Enter to drop straight into a game
+        game.pause();
+        assert!(game.game_status == GameStatus::SplashScreen);
+        assert!(game.status_changes.is_empty());
+        game.start();
+        game.pause();
+        game.start();
+        let transitions: Vec<Transition> =
+            game.status_changes.iter().map(|c| c.transition).collect();
+        assert!(transitions == [Transition::Start, Transition::Pause, Transition::Resume]);
+        assert!(game.status_changes[1].to == GameStatus::Paused);
+        // Options can only be opened from the title screen
+        game.show_options();
+        assert!(game.game_status == GameStatus::Playing);
+    }
+
+    #[test]
+    fn test_illegal_changes_are_refused() {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        game.start();
+        assert!(!game.set_pod_status(PodStatus::Ascending));
+        assert!(game.pod_status == PodStatus::Inactive);
+        assert!(!game.set_man_status(ManStatus::Inactive));
+        assert!(game.set_pod_status(PodStatus::Dropping));
+        assert!(game.pod_status == PodStatus::Dropping);
+        // The terran only comes out once the pod is down, and a new level
+        // sends them back
+        game.pod_pos_x = game.landing_pad_x;
+        game.pod_pos_y = game.window_height as f32;
+        game.asteroids.clear();
+        game.next_frame();
+        assert!(game.man_status == ManStatus::EnteringPod);
+        game.new_level(2);
+        assert!(game.man_status == ManStatus::Inactive);
+    }
+
+    #[test]
+    fn test_events() {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        game.start();
+        game.asteroids.clear();
+        game.drop_pod();
+        game.pod_pos_x = game.landing_pad_x;
+        for _ in 0..1000 {
+            game.launch_pod();
+            game.next_frame();
+        }
+        let kinds: Vec<EventKind> = game.events.iter().map(|e| e.kind).collect();
+        assert!(matches!(
+            kinds.as_slice(),
+            [
+                EventKind::GameStarted {
+                    level: 1,
+                    on_record: true
+                },
+                EventKind::PodDropped,
+                EventKind::Landed,
+                EventKind::RescueeBoarded,
+                EventKind::Launched,
+                EventKind::Docked { .. },
+                ..
+            ]
+        ));
+        assert!(game
+            .events
+            .windows(2)
+            .all(|pair| pair[0].tick <= pair[1].tick));
+        // Once published they count towards the player's statistics
+        let mut bus = EventBus::default();
+        bus.subscribe(Box::new(StatsRecorder::new(game.profiles())));
+        bus.publish(&game.events);
+        let stats = game.profiles.borrow().current().stats.clone();
+        assert!(stats.games_played == 1 && stats.pods_dropped >= 1);
+        assert!(stats.terrans_rescued() >= 1);
+        // The demo is heard, but doesn't count
+        game.restart();
+        game.events.clear();
+        game.show_attract(Stage::Demo);
+        for _ in 0..600 {
+            game.next_frame();
+        }
+        assert!(matches!(
+            game.events[0].kind,
+            EventKind::GameStarted {
+                on_record: false,
+                ..
+            }
+        ));
+        bus.publish(&game.events);
+        let after = game.profiles.borrow().current().stats.clone();
+        assert!(after.games_played == 1 && after.pods_dropped == stats.pods_dropped);
+    }
+
+    #[test]
+    fn test_quitting_mid_level() {
+        let dir = std::env::temp_dir().join(format!("xtarda2-quit-{}", std::process::id()));
+        let path = dir.join("profiles.toml");
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        game.load_profiles(&path);
+        let mut bus = EventBus::default();
+        bus.subscribe(Box::new(StatsRecorder::new(game.profiles())));
+        game.start();
+        game.drop_pod();
+        for _ in 0..120 {
+            game.next_frame();
+        }
+        // Going back to the title screen saves the game so far, time and all
+        game.restart();
+        bus.publish(&game.events);
+        let stats = Profiles::load(&path).current().stats.clone();
+        assert!(stats.games_played == 1 && stats.pods_dropped == 1);
+        assert!(stats.play_frames == 120);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_achievement_toasts() {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        let (sender, unlocked) = mpsc::channel();
+        let mut bus = EventBus::default();
+        bus.subscribe(Box::new(AchievementsRecorder::new(game.profiles(), sender)));
+        let mut finish_level_9 = |game: &mut Game| {
+            game.restart();
+            game.play_level(9, None);
+            game.level_complete();
+            bus.publish(&game.events);
+            game.events.clear();
+            for achievement in unlocked.try_iter() {
+                game.announce(achievement);
+            }
+        };
+        // Practice doesn't count
+        game.set_mode(GameMode::Practice);
+        finish_level_9(&mut game);
+        assert!(game.toasts.is_empty());
+        game.set_mode(GameMode::Classic);
+        finish_level_9(&mut game);
+        // Through to level 10 without a scrape or a lost pod on the way
+        assert!(
+            game.toasts
+                == [
+                    Achievement::Untouched,
+                    Achievement::FullHangar,
+                    Achievement::Veteran
+                ]
+        );
+        assert!(game
+            .profiles
+            .borrow()
+            .current()
+            .achievements
+            .has(Achievement::Veteran));
+        assert!(game.events.iter().any(|e| e.kind
+            == EventKind::AchievementUnlocked {
+                achievement: Achievement::Veteran
+            }));
+        // They're shown one at a time
+        for _ in 0..TOAST_SECONDS * FRAMES_PER_SECOND {
+            game.next_frame();
+        }
+        assert!(game.toasts.len() == 2);
+        game.toasts.clear();
+        // Each is only announced the first time
+        finish_level_9(&mut game);
+        assert!(game.toasts.is_empty());
+    }
+
+    #[test]
+    fn test_attract_mode() {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        game.restart();
+        let idle = attract::IDLE_SECONDS * FRAMES_PER_SECOND;
+        for _ in 1..idle {
+            game.next_frame();
+        }
+        // A key press starts the wait again
+        game.stop_attract();
+        for _ in 1..idle {
+            game.next_frame();
+        }
+        assert!(!game.in_attract_mode());
+        game.next_frame();
+        assert!(game.attract == Some(Stage::Demo));
+        assert!(game.game_status == GameStatus::Playing);
+        assert!(game.level <= attract::DEMO_LEVELS);
+        // The bot flies until the demo is over, then the screens follow
+        let mut dropped = false;
+        while game.attract == Some(Stage::Demo) {
+            dropped |= game.pod_status != PodStatus::Inactive;
+            game.pod_manoeuvre();
+            game.next_frame();
+        }
+        assert!(dropped);
+        assert!(game.attract == Some(Stage::HighScores));
+        assert!(game.game_status == GameStatus::Attract);
+        for _ in 0..Stage::HighScores.seconds() * FRAMES_PER_SECOND {
+            game.next_frame();
+        }
+        assert!(game.attract == Some(Stage::Controls));
+        game.stop_attract();
+        assert!(game.game_status == GameStatus::SplashScreen && game.level == 1);
+        // The demo never scores or unlocks anything
+        assert!(game.high_scores.entries.is_empty());
+        assert!(game.profiles.borrow().current().unlocked_levels() == 1);
+    }
+
+    // The sounds called for by the game's events so far
+    fn cued_sounds(game: &mut Game) -> Vec<SoundEvent> {
+        let (sender, sounds) = mpsc::channel();
+        let mut cues = SoundCues::new(sender, game.window_width as f32);
+        for event in game.events.drain(..) {
+            cues.notify(&event);
+        }
+        sounds.try_iter().collect()
+    }
+
+    // Blows the pod up with its middle at (x, y), returning the sound it makes
+    fn explosion_at(game: &mut Game, x: f32, y: f32) -> SoundEvent {
+        game.events.clear();
+        game.pod_status = PodStatus::Dropping;
+        game.pod_pos_x = x - game.pod_size / 2.0;
+        game.pod_pos_y = y - game.pod_size / 2.0;
+        game.explode_pod(Cause::Asteroid);
+        cued_sounds(game)[0]
+    }
+
+    #[test]
+    fn test_explosion_sounds() {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        game.start();
+        game.pods_remaining = 10;
+        // Distances are from the pod's corner to the mothership's
+        let x = game.mothership_pos_x + game.pod_size / 2.0;
+        let y = game.mothership_pos_y + game.pod_size / 2.0;
+        let close_to =
+            |sound: SoundEvent, intensity: f32| (sound.intensity - intensity).abs() < 0.001;
+        // Right by the mothership it's as loud as it gets
+        let near = explosion_at(&mut game, x, y);
+        assert!(near.sound == Sounds::Explosion && near.pitch == 1.0);
+        assert!(close_to(near, 1.0));
+        // Further down it's quieter, but never less than 0.4
+        assert!(close_to(explosion_at(&mut game, x, y + 640.0), 0.7));
+        assert!(close_to(explosion_at(&mut game, x, y + 1280.0), 0.4));
+        assert!(close_to(
+            explosion_at(&mut game, x + 1280.0, y + 1280.0),
+            0.4
+        ));
+    }
+
+    #[test]
+    fn test_sound_pitch() {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        game.start();
+        // The closer the shave, the higher the scrape
+        game.scrape(0.0);
+        game.scrape(1.0);
+        assert!((game.sounds_to_play[0].pitch - 0.8).abs() < 0.001);
+        assert!((game.sounds_to_play[1].pitch - 1.4).abs() < 0.001);
+        // Each docking bonus in a row chimes higher
+        game.men_to_rescue = 10;
+        game.events.clear();
+        for _ in 0..3 {
+            game.pod_status = PodStatus::Ascending;
+            game.pod_pos_x = game.mothership_pos_x + 10.0;
+            game.pod_pos_y = game.mothership_pos_y;
+            assert!(game.check_for_pod_docking());
+        }
+        let chimes: Vec<f32> = cued_sounds(&mut game)
+            .iter()
+            .filter(|sound| sound.sound == Sounds::Bonus)
+            .map(|sound| sound.pitch)
+            .collect();
+        assert!(chimes.len() == 3);
+        assert!(chimes[0] == 1.0 && (chimes[2] - 1.2).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_sound_panning() {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        game.start();
+        game.pods_remaining = 10;
+        let y = game.mothership_pos_y;
+        assert!(explosion_at(&mut game, 0.0, y).pan == -1.0);
+        assert!(explosion_at(&mut game, 960.0, y).pan == 0.0);
+        assert!(explosion_at(&mut game, 1920.0, y).pan == 1.0);
+        // Past the edges it's as far over as it goes
+        assert!(explosion_at(&mut game, -100.0, y).pan == -1.0);
+        assert!(explosion_at(&mut game, 2100.0, y).pan == 1.0);
+    }
+
+    #[test]
+    fn test_music_intensity() {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        assert!(game.music_context() == MusicContext::Splash);
+        assert!(game.music_intensity() == 0.0);
+        game.game_status = GameStatus::Playing;
+        game.pod_status = PodStatus::Dropping;
+        game.asteroids.clear();
+        assert!(game.music_context() == MusicContext::Level(1));
+        assert!(game.music_intensity() == 0.4);
+        for _ in 0..5 {
+            game.asteroids.push(Asteroid {
+                y_pos: game.pod_pos_y,
+                x_pos: game.pod_pos_x,
+                speed: 0.0,
+                r1: 5.0,
+                r1_offset_x: 0.0,
+                r1_offset_y: 0.0,
+                r2: 5.0,
+                r2_offset_x: 0.0,
+                r2_offset_y: 0.0,
+                r3: 5.0,
+                r3_offset_x: 0.0,
+                r3_offset_y: 0.0,
+            });
+        }
+        assert!(game.music_intensity() == 1.0);
     }
 }
 
+#[derive(Clone)]
 struct Asteroid {
     // Note all values are size_multiplier adjusted
     y_pos: f32,
@@ -88,6 +689,14 @@ struct Asteroid {
     r3_offset_y: f32,
 }
 
+impl Asteroid {
+    fn scale_blobs(&mut self, factor: f32) {
+        self.r1 *= factor;
+        self.r2 *= factor;
+        self.r3 *= factor;
+    }
+}
+
 struct Star {
     y_pos: u32,
     x_pos: u32,
@@ -95,15 +704,15 @@ struct Star {
     luminosity: u8,
 }
 
-#[derive(Eq, PartialEq)]
-enum ManStatus {
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ManStatus {
     Inactive,
     EnteringPod,
     _Dropping,
 }
 
-#[derive(Eq, PartialEq)]
-enum PodStatus {
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum PodStatus {
     Inactive,
     Dropping,
     Ascending,
@@ -112,6 +721,7 @@ enum PodStatus {
     AutoDock,
 }
 
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum Sounds {
     Explosion,
     Landed,
@@ -123,25 +733,47 @@ pub enum Sounds {
     Bonus,
 }
 
-#[derive(Eq, PartialEq)]
+/// A sound for the front end to play, along with how to play it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SoundEvent {
+    pub sound: Sounds,
+    /// Where it happened across the screen, from -1.0 (left) to 1.0 (right)
+    pub pan: f32,
+    /// From 0.0 to 1.0, scales the volume
+    pub intensity: f32,
+    /// Playback speed, where 1.0 is as recorded
+    pub pitch: f32,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
 pub enum GameStatus {
     Playing,
     SplashScreen,
     GameOver,
     NewLevel,
     Paused,
+    Options,
+    Editor,
+    LevelSelect,
+    /// The current player's statistics
+    Stats,
+    /// Every achievement, and which the current player has unlocked
+    Achievements,
+    /// The high scores or controls screen, between attract mode demos
+    Attract,
 }
 
-#[derive(Eq, PartialEq)]
+#[derive(PartialEq)]
 enum CollisionType {
     None,
-    NearMiss,
+    /// How close the miss was, from 0.0 (barely touched the margin) to 1.0
+    NearMiss(f32),
     Fatal,
 }
 
 pub struct Game {
-    pub game_status: GameStatus,
-    level: u8,
+    game_status: GameStatus,
+    level: u32,
     window_width: u32,
     window_height: u32,
     size_multiplier: f32,
@@ -158,10 +790,26 @@ pub struct Game {
     landing_pad_height: f32,
     landing_pad_width: f32,
     landing_pad_x: f32,
+    moonbase_x: f32,
     pod_size: f32,
     pod_status: PodStatus,
     pod_explosion_timer: u8,
-    pub sounds_to_play: Vec<Sounds>,
+    /// Scrapes, which change every frame. Every other sound is cued by
+    /// `events`.
+    pub sounds_to_play: Vec<SoundEvent>,
+    /// Transitions since the front end last looked, for it to react to and
+    /// clear
+    pub status_changes: Vec<StatusChange>,
+    /// What has happened since the front end last looked, for it to pass on
+    /// to whatever is listening and clear
+    pub events: Vec<Event>,
+    /// Frames played this game
+    tick: u32,
+    /// The pod was scraping past an asteroid last frame
+    scraping: bool,
+    /// Achievements to announce, the first for `toast_frames` so far
+    toasts: VecDeque<Achievement>,
+    toast_frames: u32,
     men_to_rescue: u32,
     pods_remaining: u32,
     pods_carried_over: u32,
@@ -170,17 +818,130 @@ pub struct Game {
     man_status: ManStatus,
     pub debugging_aids: bool,
     stars: Vec<Star>,
+    themes: Vec<Theme>,
+    theme: usize,
+    options_cursor: usize,
+    speccy_mode: bool,
+    audio_settings: AudioSettings,
+    levels: HashMap<u32, LevelDefinition>,
+    level_name: String,
+    rules: Rules,
+    editor: Option<Editor>,
+    /// Playing a level straight from the editor, to try it out
+    playtesting: bool,
+    /// A bot is flying the pods
+    autopiloted: bool,
+    resource_path: String,
+    rng: StdRng,
+    /// Levels are made up from a seed, so must come out the same for everyone
+    seeded: bool,
+    /// The difficulty set on the options screen
+    chosen_difficulty: Difficulty,
+    /// The difficulty this game is being played on
+    difficulty: Difficulty,
+    mode: GameMode,
+    score: u32,
+    high_scores: HighScores,
+    /// Where the last game came in the high scores, if it did
+    high_score_rank: Option<usize>,
+    /// Time spent playing this level, in frames
+    level_frames: u32,
+    /// Par for this level in time attack, in frames
+    level_par: u32,
+    /// How long each level of a time-attack run took
+    level_times: Vec<LevelTime>,
+    /// The mothership left orbit before the level was finished
+    out_of_time: bool,
+    /// Where the last time-attack run came in the best times, if it did
+    best_run_rank: Option<usize>,
+    /// Days since 1970, for the daily run
+    today: u32,
+    /// This daily run is today's first, so it counts
+    daily_scored: bool,
+    /// Level chosen on the practice level select screen
+    selected_level: u32,
+    /// Asteroids stand still while practising
+    frozen: bool,
+    /// Shared with whatever keeps the players' statistics and achievements
+    profiles: Rc<RefCell<Profiles>>,
+    /// Pods lost on this level
+    level_crashes: u32,
+    /// Pods autodocked on this level
+    level_autodocks: u32,
+    /// Frames the title screen has been left alone for
+    idle_frames: u32,
+    /// What attract mode is showing, if it's running
+    attract: Option<Stage>,
+    /// Frames the current attract mode stage has been showing for
+    attract_frames: u32,
+    /// Flies the pods in attract mode demos
+    demo_pilot: Option<Bot>,
 }
 
 fn distance(x1: f32, y1: f32, x2: f32, y2: f32) -> f32 {
     f32::sqrt((x2 - x1).powf(2.0) + (y2 - y1).powf(2.0))
 }
 
+/// Moves the mothership one frame, turning round near the edges
+pub fn move_mothership(
+    x: f32,
+    direction: i8,
+    window_width: f32,
+    mothership_width: f32,
+    size_multiplier: f32,
+) -> (f32, i8) {
+    let mut direction = direction;
+    if (x > window_width - (mothership_width + 50.0) && direction > 0)
+        || (x < 50.0 && direction < 0)
+    {
+        direction = -direction;
+    }
+    (x + direction as f32 * size_multiplier, direction)
+}
+
+/// Moves an asteroid one frame, wrapping round when it leaves the screen
+pub fn move_asteroid(x: f32, speed: f32, window_width: f32, size_multiplier: f32) -> f32 {
+    let x = x + speed;
+    if speed > 0.0 && x > window_width {
+        return -150.0 * size_multiplier;
+    }
+    if speed < 0.0 && x < -150.0 * size_multiplier {
+        return window_width;
+    }
+    x
+}
+
+// Generated asteroid fields that can't be got through are made again, up
+// to this many times
+const MAX_REROLLS: usize = 20;
+// Time allowed to get a pod down to the pad and back up again
+const SOLVE_SECONDS: f32 = 20.0;
+// Points for each man rescued, times the level number
+const RESCUE_POINTS: u32 = 100;
+// Extra points for docking without autodock
+const DOCKING_POINTS: u32 = 50;
+// High scores shown at the end of a game
+const HIGH_SCORES_SHOWN: usize = 5;
+// Streakers move this many times faster than the fastest asteroids
+const STREAKER_SPEED: f32 = 2.0;
+// How far apart the two asteroids in a pair fly
+const PAIR_GAP: f32 = 220.0;
+// Pods to start with when continuing from a later level, on top of the
+// level's own
+const CONTINUE_PODS: u32 = 3;
+// Levels listed at once on the level select screen
+const LEVELS_LISTED: u32 = 5;
+// Where the options screen's list starts and how far apart its lines are
+const OPTIONS_TOP: f32 = 310.0;
+const OPTIONS_SPACING: f32 = 40.0;
+// How long each newly unlocked achievement is announced for
+const TOAST_SECONDS: u32 = 3;
+
 impl Game {
     pub fn new(window_width: u32, window_height: u32, resource_path: String) -> Game {
         let size_multiplier = window_width as f32 * 0.000_520_833_36;
         let pad_width = 250.0 * size_multiplier;
-        let font = Font::from_file(&(resource_path + "/zx-spectrum.ttf")).unwrap();
+        let font = Font::from_file(&(resource_path.clone() + "/zx-spectrum.ttf")).unwrap();
         Game {
             game_status: GameStatus::SplashScreen,
             level: 1,
@@ -200,10 +961,17 @@ impl Game {
             landing_pad_height: 20.0 * size_multiplier,
             landing_pad_width: pad_width,
             landing_pad_x: window_width as f32 / 2.0 - (pad_width / 2.0),
+            moonbase_x: window_width as f32 * 0.75,
             pod_size: 20.0 * size_multiplier,
             pod_status: PodStatus::Inactive,
             pod_explosion_timer: 0,
             sounds_to_play: vec![],
+            status_changes: vec![],
+            events: vec![],
+            tick: 0,
+            scraping: false,
+            toasts: VecDeque::new(),
+            toast_frames: 0,
             men_to_rescue: 5,
             pods_remaining: 0,
             pods_carried_over: 0,
@@ -212,15 +980,55 @@ impl Game {
             man_status: ManStatus::Inactive,
             debugging_aids: false,
             stars: Vec::new(),
+            themes: Theme::load_all(Path::new(&(resource_path.clone() + "/themes"))),
+            theme: 0,
+            options_cursor: 0,
+            speccy_mode: false,
+            audio_settings: AudioSettings::default(),
+            levels: LevelDefinition::load_all(Path::new(&(resource_path.clone() + "/levels"))),
+            level_name: String::new(),
+            rules: Rules::default(),
+            editor: None,
+            playtesting: false,
+            autopiloted: false,
+            resource_path,
+            rng: StdRng::from_entropy(),
+            seeded: false,
+            chosen_difficulty: Difficulty::default(),
+            difficulty: Difficulty::default(),
+            mode: GameMode::default(),
+            score: 0,
+            high_scores: HighScores::default(),
+            high_score_rank: None,
+            level_frames: 0,
+            level_par: 0,
+            level_times: Vec::new(),
+            out_of_time: false,
+            best_run_rank: None,
+            today: daily::today(),
+            daily_scored: false,
+            selected_level: 1,
+            frozen: false,
+            profiles: Rc::default(),
+            level_crashes: 0,
+            level_autodocks: 0,
+            idle_frames: 0,
+            attract: None,
+            attract_frames: 0,
+            demo_pilot: None,
         }
     }
 
-    fn draw_mothership(&mut self, window: &mut RenderWindow) {
+    pub fn theme(&self) -> &Theme {
+        &self.themes[self.theme]
+    }
+
+    fn draw_mothership(&mut self, window: &mut dyn RenderTarget) {
         let mut fin = RectangleShape::with_size(Vector2f::new(
             15.0 * self.size_multiplier,
             15.0 * self.size_multiplier,
         ));
-        fin.set_fill_color(Color::rgb(0, 255, 0));
+        fin.set_fill_color(self.theme().mothership);
         fin.set_position(Vector2f::new(
             self.mothership_pos_x,
             self.mothership_pos_y + 15.0 * self.size_multiplier,
@@ -235,7 +1043,7 @@ impl Game {
             50.0 * self.size_multiplier,
             30.0 * self.size_multiplier,
         ));
-        body.set_fill_color(Color::rgb(0, 255, 0));
+        body.set_fill_color(self.theme().mothership);
         body.set_position(Vector2f::new(
             self.mothership_pos_x + 15.0 * self.size_multiplier,
             self.mothership_pos_y,
@@ -243,7 +1051,7 @@ impl Game {
         window.draw(&body);
     }
 
-    fn draw_man(&mut self, window: &mut RenderWindow) {
+    fn draw_man(&mut self, window: &mut dyn RenderTarget) {
         if self.man_status == ManStatus::Inactive {
             return;
         }
@@ -251,46 +1059,46 @@ impl Game {
             6.0 * self.size_multiplier,
             20.0 * self.size_multiplier,
         ));
-        man.set_fill_color(Color::rgb(0, 255, 0));
+        man.set_fill_color(self.theme().man);
         man.set_position(Vector2f::new(self.man_pos_x, self.man_pos_y));
         window.draw(&man);
     }
 
-    fn draw_stars(&mut self, window: &mut RenderWindow) {
+    fn draw_stars(&mut self, window: &mut dyn RenderTarget) {
         for star in &self.stars {
             let mut circle = CircleShape::new(star.radius as f32, 4);
-            circle.set_fill_color(Color::rgb(0, star.luminosity, 0));
+            circle.set_fill_color(self.theme().star(star.luminosity));
             circle.set_position(Vector2f::new(star.x_pos as f32, star.y_pos as f32));
             window.draw(&circle);
         }
     }
 
-    fn draw_ground(&mut self, window: &mut RenderWindow) {
-        let ground_colour = 96;
-        let hill_colour = 64;
+    fn draw_ground(&mut self, window: &mut dyn RenderTarget) {
+        let ground_colour = self.theme().ground;
+        let hill_colour = self.theme().hills;
         let mut hill1 = CircleShape::new(150.0 * self.size_multiplier, 3);
-        hill1.set_fill_color(Color::rgb(0, hill_colour, 0));
+        hill1.set_fill_color(hill_colour);
         hill1.set_position(Vector2f::new(
             0.0,
             self.window_height as f32 - 150.0 * self.size_multiplier,
         ));
         window.draw(&hill1);
         let mut hill2 = CircleShape::new(300.0 * self.size_multiplier, 3);
-        hill2.set_fill_color(Color::rgb(0, hill_colour, 0));
+        hill2.set_fill_color(hill_colour);
         hill2.set_position(Vector2f::new(
             -300.0 * self.size_multiplier,
             self.window_height as f32 - 300.0 * self.size_multiplier,
         ));
         window.draw(&hill2);
         let mut hill3 = CircleShape::new(240.0 * self.size_multiplier, 3);
-        hill3.set_fill_color(Color::rgb(0, hill_colour, 0));
+        hill3.set_fill_color(hill_colour);
         hill3.set_position(Vector2f::new(
             self.window_width as f32 - 400.0 * self.size_multiplier,
             self.window_height as f32 - 240.0 * self.size_multiplier,
         ));
         window.draw(&hill3);
         let mut hill4 = CircleShape::new(340.0 * self.size_multiplier, 3);
-        hill4.set_fill_color(Color::rgb(0, hill_colour, 0));
+        hill4.set_fill_color(hill_colour);
         hill4.set_position(Vector2f::new(
             self.window_width as f32 - 370.0 * self.size_multiplier,
             self.window_height as f32 - 340.0 * self.size_multiplier,
@@ -298,7 +1106,7 @@ impl Game {
         window.draw(&hill4);
         let mut ground =
             RectangleShape::with_size(Vector2f::new(self.window_width as f32, self.ground_height));
-        ground.set_fill_color(Color::rgb(0, ground_colour, 0));
+        ground.set_fill_color(ground_colour);
         ground.set_position(Vector2f::new(
             0.0,
             self.window_height as f32 - 40.0 * self.size_multiplier,
@@ -306,12 +1114,12 @@ impl Game {
         window.draw(&ground);
     }
 
-    fn draw_landing_pad(&mut self, window: &mut RenderWindow) {
+    fn draw_landing_pad(&mut self, window: &mut dyn RenderTarget) {
         let mut pad = RectangleShape::with_size(Vector2f::new(
             self.landing_pad_width,
             self.landing_pad_height,
         ));
-        pad.set_fill_color(Color::rgb(0, 120, 0));
+        pad.set_fill_color(self.theme().pad);
         pad.set_position(Vector2f::new(
             self.landing_pad_x,
             self.window_height as f32 - self.ground_height - self.landing_pad_height,
@@ -319,34 +1127,34 @@ impl Game {
         window.draw(&pad);
     }
 
-    fn draw_moonbase(&mut self, window: &mut RenderWindow) {
+    fn draw_moonbase(&mut self, window: &mut dyn RenderTarget) {
         let mut moonbase = CircleShape::new(100.0 * self.size_multiplier, 32);
-        moonbase.set_fill_color(Color::rgb(0, 110, 0));
+        moonbase.set_fill_color(self.theme().moonbase);
         moonbase.set_position(Vector2f::new(
-            self.window_width as f32 * 0.75,
+            self.moonbase_x,
             self.window_height as f32 - 100.0 * self.size_multiplier - self.ground_height,
         ));
         window.draw(&moonbase);
     }
 
-    fn draw_asteroids(&mut self, window: &mut RenderWindow) {
+    fn draw_asteroids(&mut self, window: &mut dyn RenderTarget) {
         for asteroid in &self.asteroids {
             let mut blob3 = CircleShape::new(asteroid.r3, 8);
-            blob3.set_fill_color(Color::rgb(0, 80, 0));
+            blob3.set_fill_color(self.theme().asteroid[2]);
             blob3.set_position(Vector2f::new(
                 asteroid.x_pos + asteroid.r3_offset_x,
                 asteroid.y_pos + asteroid.r3_offset_y,
             ));
             window.draw(&blob3);
             let mut blob2 = CircleShape::new(asteroid.r2, 8);
-            blob2.set_fill_color(Color::rgb(0, 100, 0));
+            blob2.set_fill_color(self.theme().asteroid[1]);
             blob2.set_position(Vector2f::new(
                 asteroid.x_pos + asteroid.r2_offset_x,
                 asteroid.y_pos + asteroid.r2_offset_y,
             ));
             window.draw(&blob2);
             let mut blob1 = CircleShape::new(asteroid.r1, 8);
-            blob1.set_fill_color(Color::rgb(0, 120, 0));
+            blob1.set_fill_color(self.theme().asteroid[0]);
             blob1.set_position(Vector2f::new(
                 asteroid.x_pos + asteroid.r1_offset_x,
                 asteroid.y_pos + asteroid.r1_offset_y,
@@ -355,36 +1163,36 @@ impl Game {
         }
     }
 
-    fn draw_pod(&mut self, window: &mut RenderWindow) {
+    fn draw_pod(&mut self, window: &mut dyn RenderTarget) {
         if self.pod_status == PodStatus::Exploding {
             let mut rng = rand::thread_rng();
             let radius = rng.gen_range(20.0 * self.size_multiplier..200.0 * self.size_multiplier);
             let mut explosion = CircleShape::new(radius, 32);
             let lum = rng.gen_range(200..255);
-            explosion.set_fill_color(Color::rgb(0, lum, 0));
+            explosion.set_fill_color(self.theme().explosion_flash(lum));
             explosion.set_position(Vector2f::new(
                 self.pod_pos_x - radius + self.pod_size / 2.0,
                 self.pod_pos_y - radius + self.pod_size / 2.0,
             ));
             window.draw(&explosion);
-            self.pod_explosion_timer += 1;
-            if self.pod_explosion_timer > 20 {
-                self.pod_explosion_timer = 0;
-                self.pod_status = PodStatus::Inactive;
-            }
             return;
         }
         let mut pod = RectangleShape::with_size(Vector2f::new(self.pod_size, self.pod_size));
-        pod.set_fill_color(Color::rgb(0, 255, 0));
+        pod.set_fill_color(self.theme().pod);
         pod.set_position(Vector2f::new(self.pod_pos_x, self.pod_pos_y));
         window.draw(&pod);
     }
 
-    fn draw_status_bar(&mut self, window: &mut RenderWindow) {
+    fn draw_status_bar(&mut self, window: &mut dyn RenderTarget) {
+        let pods = if self.mode == GameMode::Practice {
+            "Practice".to_string()
+        } else {
+            format!("Pods Left: {}", self.pods_remaining)
+        };
         let mut text = Text::new(
             &format!(
-                "Level: {}  Terrans to Rescue: {}  Pods Left: {}",
-                self.level, self.men_to_rescue, self.pods_remaining
+                "Level: {}  Terrans to Rescue: {}  {}",
+                self.level, self.men_to_rescue, pods
             ),
             &self.font,
             (self.window_width as f32 * 0.015625) as u32,
@@ -393,38 +1201,235 @@ impl Game {
             200.0 * self.size_multiplier,
             20.0 * self.size_multiplier,
         ));
-        text.set_fill_color(Color::rgb(0, 200, 0));
+        text.set_fill_color(self.theme().text);
         window.draw(&text);
-    }
-
-    fn draw_splash_screen(&mut self, window: &mut RenderWindow) {
-        let mut text = Text::new(
-            &"Xtarda Rescue!".to_string(),
-            &self.font,
-            (self.window_width as f32 * 0.05) as u32,
-        );
+        let mut line = match self.mode {
+            GameMode::Practice if self.frozen => "F unfreezes asteroids  R restarts".to_string(),
+            GameMode::Practice => "F freezes asteroids  R restarts".to_string(),
+            _ => format!("Score: {}", self.score),
+        };
+        if self.mode == GameMode::TimeAttack {
+            let left = timeattack::limit_frames(self.level_par).saturating_sub(self.level_frames);
+            line += &format!(
+                "  Time Left: {}  Par: {}",
+                timeattack::format_time(left),
+                timeattack::format_time(self.level_par)
+            );
+        }
+        text.set_string(&line);
         text.set_position(Vector2f::new(
-            150.0 * self.size_multiplier,
             200.0 * self.size_multiplier,
+            55.0 * self.size_multiplier,
         ));
-        text.set_fill_color(Color::rgb(0, 200, 0));
         window.draw(&text);
+    }
+
+    fn draw_splash_screen(&mut self, window: &mut dyn RenderTarget) {
+        self.draw_title("Xtarda Rescue!", window);
         self.draw_message("(c) 1982 Sonic Software", window);
+        self.draw_line(&format!("Mode: < {} >", self.mode.name()), 430.0, window);
+        if self.mode == GameMode::Daily {
+            let mut line = format!(
+                "{}  Streak: {}",
+                daily::format_date(self.today),
+                daily::streak(&self.high_scores.daily, self.today)
+            );
+            if let Some(result) = self.high_scores.daily_result(self.today) {
+                line += &format!("  Today: {}", result.score);
+            }
+            self.draw_line(&line, 465.0, window);
+        }
+        self.draw_line("Press O for options, S for statistics", 500.0, window);
+        self.draw_line("Press E to edit levels, A for achievements", 550.0, window);
         self.draw_press_enter(window);
     }
 
-    fn draw_new_level_screen(&mut self, window: &mut RenderWindow) {
+    fn draw_options_screen(&mut self, window: &mut dyn RenderTarget) {
+        self.draw_title("Options", window);
+        for (idx, item) in OptionItem::ALL.iter().enumerate() {
+            let value = match item {
+                OptionItem::Profile => self.profiles.borrow().current_name().to_string(),
+                OptionItem::Theme => self.theme().name.clone(),
+                OptionItem::Display => {
+                    if self.speccy_mode {
+                        "ZX Spectrum".to_string()
+                    } else {
+                        "Modern".to_string()
+                    }
+                }
+                OptionItem::MasterVolume => self.audio_settings.master.to_string(),
+                OptionItem::MusicVolume => self.audio_settings.music.to_string(),
+                OptionItem::SfxVolume => self.audio_settings.sfx.to_string(),
+                OptionItem::Difficulty => self.chosen_difficulty.preset.name().to_string(),
+                OptionItem::Curve(Setting::DockingBonus) => {
+                    if self.chosen_difficulty.docking_bonus {
+                        "On".to_string()
+                    } else {
+                        "Off".to_string()
+                    }
+                }
+                OptionItem::Curve(setting) => {
+                    format!("{}%", self.chosen_difficulty.value(*setting))
+                }
+            };
+            let cursor = if idx == self.options_cursor { ">" } else { " " };
+            self.draw_line(
+                &format!("{} {}: < {} >", cursor, item.label(), value),
+                OPTIONS_TOP + OPTIONS_SPACING * idx as f32,
+                window,
+            );
+        }
+        // There are too many options to leave room for the usual prompt
+        self.draw_line(
+            "Press ENTER to continue",
+            OPTIONS_TOP + OPTIONS_SPACING * (OptionItem::ALL.len() as f32 + 0.5),
+            window,
+        );
+    }
+
+    fn draw_stats_screen(&mut self, window: &mut dyn RenderTarget) {
+        self.draw_title("Statistics", window);
+        let profiles = self.profiles.borrow();
+        let profile = profiles.current();
+        let mut lines = vec![format!("{:<22}{}", "Player", profiles.current_name())];
+        lines.extend(
+            profile
+                .stats
+                .lines()
+                .into_iter()
+                .map(|(label, value)| format!("{:<22}{}", label, value)),
+        );
+        for (idx, line) in lines.iter().enumerate() {
+            self.draw_line(line, OPTIONS_TOP + OPTIONS_SPACING * idx as f32, window);
+        }
+        self.draw_line(
+            "Press ENTER to continue",
+            OPTIONS_TOP + OPTIONS_SPACING * (lines.len() as f32 + 0.5),
+            window,
+        );
+    }
+
+    fn draw_achievements_screen(&mut self, window: &mut dyn RenderTarget) {
+        self.draw_title("Achievements", window);
+        let profiles = self.profiles.borrow();
+        let achievements = &profiles.current().achievements;
+        // Unlocked ones are starred, like the stars on the level select
+        // screen
+        for (idx, achievement) in Achievement::ALL.iter().enumerate() {
+            let star = if achievements.has(*achievement) {
+                "*"
+            } else {
+                "."
+            };
+            let y = OPTIONS_TOP + OPTIONS_SPACING * 2.0 * idx as f32;
+            self.draw_line(&format!("{} {}", star, achievement.name()), y, window);
+            self.draw_line(
+                &format!("  {}", achievement.description()),
+                y + OPTIONS_SPACING,
+                window,
+            );
+        }
+        self.draw_line(
+            "Press ENTER to continue",
+            OPTIONS_TOP + OPTIONS_SPACING * (2.0 * Achievement::ALL.len() as f32 + 0.5),
+            window,
+        );
+    }
+
+    // Announces the achievement most recently unlocked, over whatever else
+    // is on screen
+    fn draw_toast(&self, window: &mut dyn RenderTarget) {
+        let Some(achievement) = self.toasts.front() else {
+            return;
+        };
         let mut text = Text::new(
-            &format!("Level {}", self.level),
+            &format!("Achievement unlocked: {}", achievement.name()),
             &self.font,
-            (self.window_width as f32 * 0.05) as u32,
+            (self.window_width as f32 * 0.02) as u32,
         );
+        let width = text.global_bounds().width;
         text.set_position(Vector2f::new(
-            150.0 * self.size_multiplier,
-            200.0 * self.size_multiplier,
+            (self.window_width as f32 - width) / 2.0,
+            70.0 * self.size_multiplier,
         ));
-        text.set_fill_color(Color::rgb(0, 200, 0));
+        text.set_fill_color(self.theme().message);
         window.draw(&text);
+    }
+
+    fn draw_level_select_screen(&mut self, window: &mut dyn RenderTarget) {
+        let title = if self.mode == GameMode::Practice {
+            "Practice"
+        } else {
+            "Continue"
+        };
+        self.draw_title(title, window);
+        let profiles = self.profiles.borrow();
+        let profile = profiles.current();
+        let unlocked = profile.unlocked_levels();
+        self.draw_line(
+            &format!(
+                "{}: levels 1 to {} unlocked, {} stars",
+                profiles.current_name(),
+                unlocked,
+                profile.total_stars()
+            ),
+            320.0,
+            window,
+        );
+        // A few levels either side of the one chosen, with their stars for
+        // no crashes, no autodock and being under par
+        let first = self
+            .selected_level
+            .saturating_sub(LEVELS_LISTED / 2)
+            .clamp(1, unlocked.saturating_sub(LEVELS_LISTED - 1).max(1));
+        let last = first.saturating_add(LEVELS_LISTED - 1).min(unlocked);
+        for (idx, level) in (first..=last).enumerate() {
+            let cursor = if level == self.selected_level {
+                ">"
+            } else {
+                " "
+            };
+            let name = self
+                .levels
+                .get(&level)
+                .map(|l| l.name.clone())
+                .unwrap_or_default();
+            self.draw_line(
+                &format!(
+                    "{} Level {:<3} {}  {}",
+                    cursor,
+                    level,
+                    profile.stars(level),
+                    name
+                ),
+                370.0 + 36.0 * idx as f32,
+                window,
+            );
+        }
+        if self.mode == GameMode::Practice {
+            let frozen = if self.frozen { "On" } else { "Off" };
+            self.draw_line(
+                &format!("Freeze asteroids: {} (F)  R restarts", frozen),
+                555.0,
+                window,
+            );
+        } else {
+            self.draw_line(
+                &format!("Starts with {} extra pods", CONTINUE_PODS),
+                555.0,
+                window,
+            );
+        }
+        self.draw_press_enter(window);
+    }
+
+    fn draw_new_level_screen(&mut self, window: &mut dyn RenderTarget) {
+        let title = if self.level_name.is_empty() {
+            format!("Level {}", self.level)
+        } else {
+            format!("Level {}: {}", self.level, self.level_name)
+        };
+        self.draw_title(&title, window);
         match 1.cmp(&self.pods_carried_over) {
             Ordering::Greater => {}
             Ordering::Equal => {
@@ -440,37 +1445,198 @@ impl Game {
         self.draw_press_enter(window);
     }
 
-    fn draw_pause_screen(&mut self, window: &mut RenderWindow) {
-        let mut text = Text::new(
-            "Paused",
-            &self.font,
-            (self.window_width as f32 * 0.05) as u32,
-        );
-        text.set_position(Vector2f::new(
-            150.0 * self.size_multiplier,
-            200.0 * self.size_multiplier,
-        ));
-        text.set_fill_color(Color::rgb(0, 200, 0));
-        window.draw(&text);
+    fn draw_pause_screen(&mut self, window: &mut dyn RenderTarget) {
+        self.draw_title("Paused", window);
         self.draw_press_enter(window);
     }
 
-    fn draw_game_over_screen(&mut self, window: &mut RenderWindow) {
-        let mut text = Text::new(
-            &"Game Over".to_string(),
-            &self.font,
-            (self.window_width as f32 * 0.05) as u32,
+    fn draw_game_over_screen(&mut self, window: &mut dyn RenderTarget) {
+        match self.mode {
+            GameMode::TimeAttack => {
+                self.draw_time_attack_results(window);
+                return;
+            }
+            GameMode::Daily => {
+                self.draw_daily_results(window);
+                return;
+            }
+            _ => {}
+        }
+        self.draw_title("Game Over", window);
+        self.draw_message(
+            &format!(
+                "Score: {} ({}, {})",
+                self.score,
+                self.mode.name(),
+                self.difficulty.preset.name()
+            ),
+            window,
         );
+        self.draw_high_score_table(&self.difficulty, self.high_score_rank, window);
+        self.draw_restart_yn(window);
+    }
+
+    // The top scores for this mode and difficulty, pointing out `rank`
+    fn draw_high_score_table(
+        &self,
+        difficulty: &Difficulty,
+        rank: Option<usize>,
+        window: &mut dyn RenderTarget,
+    ) {
+        let top = self.high_scores.top(self.mode, difficulty);
+        for (idx, entry) in top.iter().take(HIGH_SCORES_SHOWN).enumerate() {
+            let marker = if Some(idx) == rank { ">" } else { " " };
+            self.draw_line(
+                &format!(
+                    "{} {}. {:>6}  Level {}",
+                    marker,
+                    idx + 1,
+                    entry.score,
+                    entry.level
+                ),
+                410.0 + 40.0 * idx as f32,
+                window,
+            );
+        }
+    }
+
+    fn draw_demo_banner(&self, window: &mut dyn RenderTarget) {
+        let mut text = Text::new("DEMO", &self.font, (self.window_width as f32 * 0.05) as u32);
+        let width = text.global_bounds().width;
         text.set_position(Vector2f::new(
-            150.0 * self.size_multiplier,
-            200.0 * self.size_multiplier,
+            (self.window_width as f32 - width) / 2.0,
+            120.0 * self.size_multiplier,
         ));
-        text.set_fill_color(Color::rgb(0, 200, 0));
+        text.set_fill_color(self.theme().text);
         window.draw(&text);
+    }
+
+    fn draw_attract_screen(&mut self, window: &mut dyn RenderTarget) {
+        let title = if self.attract == Some(Stage::HighScores) {
+            "High Scores"
+        } else {
+            "Controls"
+        };
+        self.draw_title(title, window);
+        if self.attract == Some(Stage::HighScores) {
+            let difficulty = self
+                .mode
+                .fixed_difficulty()
+                .unwrap_or(self.chosen_difficulty);
+            self.draw_message(
+                &format!("{}, {}", self.mode.name(), difficulty.preset.name()),
+                window,
+            );
+            self.draw_high_score_table(&difficulty, None, window);
+        } else {
+            for (idx, (keys, action)) in attract::CONTROLS.iter().enumerate() {
+                self.draw_line(
+                    &format!("{:<16}{}", keys, action),
+                    330.0 + 36.0 * idx as f32,
+                    window,
+                );
+            }
+        }
+        self.draw_line("Press any key", 650.0, window);
+    }
+
+    fn draw_time_attack_results(&mut self, window: &mut dyn RenderTarget) {
+        let title = if self.out_of_time {
+            "Out of Time"
+        } else if self.level_times.len() as u32 == timeattack::LEVELS {
+            "Run Complete"
+        } else {
+            "Game Over"
+        };
+        self.draw_title(title, window);
+        if self.out_of_time {
+            self.draw_line("The mothership has left orbit", 300.0, window);
+        }
+        for (idx, time) in self.level_times.iter().enumerate() {
+            self.draw_line(
+                &format!(
+                    "Level {}  {}  (par {}){}",
+                    time.level,
+                    timeattack::format_time(time.frames),
+                    timeattack::format_time(time.par),
+                    if time.under_par() { "  *" } else { "" }
+                ),
+                350.0 + 35.0 * idx as f32,
+                window,
+            );
+        }
+        let total: u32 = self.level_times.iter().map(|t| t.frames).sum();
+        self.draw_line(
+            &format!(
+                "Total {}  Score {}",
+                timeattack::format_time(total),
+                self.score
+            ),
+            550.0,
+            window,
+        );
+        let best = match self.high_scores.fastest(&self.difficulty).first() {
+            Some(run) => timeattack::format_time(run.frames),
+            None => "none yet".to_string(),
+        };
+        let new_best = if self.best_run_rank == Some(0) {
+            "  New best!"
+        } else {
+            ""
+        };
+        self.draw_line(
+            &format!(
+                "Best run ({}): {}{}",
+                self.difficulty.preset.name(),
+                best,
+                new_best
+            ),
+            590.0,
+            window,
+        );
         self.draw_restart_yn(window);
     }
 
-    fn draw_press_enter(&self, window: &mut RenderWindow) {
+    fn draw_daily_results(&mut self, window: &mut dyn RenderTarget) {
+        let title = format!("Daily Run {}", daily::format_date(self.today));
+        self.draw_title(&title, window);
+        let scored = if self.daily_scored {
+            ""
+        } else {
+            " - not scored, today's run was already played"
+        };
+        self.draw_message(&format!("Score: {}{}", self.score, scored), window);
+        self.draw_line(
+            &format!(
+                "Streak: {} days",
+                daily::streak(&self.high_scores.daily, self.today)
+            ),
+            410.0,
+            window,
+        );
+        // The last few days, most recent first
+        let recent: Vec<String> = self
+            .high_scores
+            .daily
+            .iter()
+            .rev()
+            .take(3)
+            .map(|r| {
+                format!(
+                    "{}  {:>6}  Level {}",
+                    daily::format_date(r.day),
+                    r.score,
+                    r.level
+                )
+            })
+            .collect();
+        for (idx, line) in recent.iter().enumerate() {
+            self.draw_line(line, 470.0 + 40.0 * idx as f32, window);
+        }
+        self.draw_restart_yn(window);
+    }
+
+    fn draw_press_enter(&self, window: &mut dyn RenderTarget) {
         let mut text = Text::new(
             &"Press ENTER to continue".to_string(),
             &self.font,
@@ -480,21 +1646,41 @@ impl Game {
             150.0 * self.size_multiplier,
             600.0 * self.size_multiplier,
         ));
-        text.set_fill_color(Color::rgb(0, 150, 0));
+        text.set_fill_color(self.theme().prompt);
         window.draw(&text);
     }
 
-    fn draw_message(&self, msg: &str, window: &mut RenderWindow) {
+    fn draw_message(&self, msg: &str, window: &mut dyn RenderTarget) {
         let mut text = Text::new(msg, &self.font, (self.window_width as f32 * 0.02) as u32);
         text.set_position(Vector2f::new(
             150.0 * self.size_multiplier,
             350.0 * self.size_multiplier,
         ));
-        text.set_fill_color(Color::rgb(0, 120, 0));
+        text.set_fill_color(self.theme().message);
+        window.draw(&text);
+    }
+
+    fn draw_title(&self, title: &str, window: &mut dyn RenderTarget) {
+        let mut text = Text::new(title, &self.font, (self.window_width as f32 * 0.05) as u32);
+        text.set_position(Vector2f::new(
+            150.0 * self.size_multiplier,
+            200.0 * self.size_multiplier,
+        ));
+        text.set_fill_color(self.theme().text);
+        window.draw(&text);
+    }
+
+    fn draw_line(&self, msg: &str, y: f32, window: &mut dyn RenderTarget) {
+        let mut text = Text::new(msg, &self.font, (self.window_width as f32 * 0.02) as u32);
+        text.set_position(Vector2f::new(
+            150.0 * self.size_multiplier,
+            y * self.size_multiplier,
+        ));
+        text.set_fill_color(self.theme().prompt);
         window.draw(&text);
     }
 
-    fn draw_restart_yn(&self, window: &mut RenderWindow) {
+    fn draw_restart_yn(&self, window: &mut dyn RenderTarget) {
         let mut text = Text::new(
             &"Restart? Y/N".to_string(),
             &self.font,
@@ -502,13 +1688,13 @@ impl Game {
         );
         text.set_position(Vector2f::new(
             150.0 * self.size_multiplier,
-            500.0 * self.size_multiplier,
+            650.0 * self.size_multiplier,
         ));
-        text.set_fill_color(Color::rgb(0, 150, 0));
+        text.set_fill_color(self.theme().prompt);
         window.draw(&text);
     }
 
-    pub fn draw_screen(&mut self, window: &mut RenderWindow) {
+    pub fn draw_screen(&mut self, window: &mut dyn RenderTarget) {
         match self.game_status {
             GameStatus::Playing => {
                 self.draw_stars(window);
@@ -522,6 +1708,9 @@ impl Game {
                 if self.pod_status != PodStatus::Inactive {
                     self.draw_pod(window);
                 }
+                if self.attract == Some(Stage::Demo) {
+                    self.draw_demo_banner(window);
+                }
             }
             GameStatus::SplashScreen => {
                 self.draw_splash_screen(window);
@@ -535,56 +1724,645 @@ impl Game {
             GameStatus::Paused => {
                 self.draw_pause_screen(window);
             }
+            GameStatus::Options => {
+                self.draw_options_screen(window);
+            }
+            GameStatus::Editor => {
+                self.draw_editor(window);
+            }
+            GameStatus::LevelSelect => {
+                self.draw_level_select_screen(window);
+            }
+            GameStatus::Stats => {
+                self.draw_stats_screen(window);
+            }
+            GameStatus::Achievements => {
+                self.draw_achievements_screen(window);
+            }
+            GameStatus::Attract => {
+                self.draw_attract_screen(window);
+            }
         }
+        self.draw_toast(window);
     }
 
-    pub fn restart(&mut self) {
-        self.new_level(1);
-        self.game_status = GameStatus::SplashScreen;
-        self.pod_status = PodStatus::Inactive;
-    }
+    fn draw_editor(&mut self, window: &mut dyn RenderTarget) {
+        let Some(editor) = self.editor.take() else {
+            return;
+        };
+        self.draw_stars(window);
+        self.draw_mothership(window);
+        self.draw_moonbase(window);
+        self.draw_landing_pad(window);
+        self.draw_ground(window);
+        self.draw_asteroids(window);
+        let font_size = (self.window_width as f32 * 0.0125) as u32;
+        for (idx, (asteroid, row)) in self
+            .asteroids
+            .iter()
+            .zip(&editor.level.asteroids)
+            .enumerate()
+        {
+            if let Some(Selection::Blob(selected, blob)) = editor.selected {
+                if selected == idx {
+                    let (radius, x, y) = match blob {
+                        0 => (asteroid.r1, asteroid.r1_offset_x, asteroid.r1_offset_y),
+                        1 => (asteroid.r2, asteroid.r2_offset_x, asteroid.r2_offset_y),
+                        _ => (asteroid.r3, asteroid.r3_offset_x, asteroid.r3_offset_y),
+                    };
+                    let mut outline = CircleShape::new(radius, 16);
+                    outline.set_fill_color(Color::TRANSPARENT);
+                    outline.set_outline_color(self.theme().prompt);
+                    outline.set_outline_thickness(2.0);
+                    outline.set_position(Vector2f::new(asteroid.x_pos + x, asteroid.y_pos + y));
+                    window.draw(&outline);
+                }
+            }
+            let speed = if row.speed < 0.0 {
+                format!("<{}", -row.speed)
+            } else {
+                format!("{}>", row.speed)
+            };
+            let mut text = Text::new(&speed, &self.font, font_size);
+            text.set_position(Vector2f::new(
+                asteroid.x_pos + asteroid.r1_offset_x,
+                asteroid.y_pos + asteroid.r1_offset_y,
+            ));
+            text.set_fill_color(self.theme().text);
+            window.draw(&text);
+        }
+        let mothership = if editor.level.mothership_speed < 0 {
+            format!("<{}", -editor.level.mothership_speed)
+        } else {
+            format!("{}>", editor.level.mothership_speed)
+        };
+        let lines = [
+            format!(
+                "Editing level {}  Mothership: {}  {}",
+                editor.number, mothership, editor.message
+            ),
+            "Click: add/move  Right drag: resize  Wheel: speed  Del: delete".to_string(),
+            "Up/Down: mothership speed  R: reverse  PgUp/PgDn: level  S: save  Enter: play"
+                .to_string(),
+        ];
+        for (idx, line) in lines.iter().enumerate() {
+            let mut text = Text::new(line, &self.font, font_size);
+            text.set_position(Vector2f::new(
+                200.0 * self.size_multiplier,
+                (20.0 + 30.0 * idx as f32) * self.size_multiplier,
+            ));
+            text.set_fill_color(self.theme().text);
+            window.draw(&text);
+        }
+        self.editor = Some(editor);
+    }
+
+    pub fn audio_settings(&self) -> AudioSettings {
+        self.audio_settings
+    }
+
+    /// Whether to draw through the ZX Spectrum display emulation
+    pub fn speccy_mode(&self) -> bool {
+        self.speccy_mode
+    }
+
+    /// Which music should be playing
+    pub fn music_context(&self) -> MusicContext {
+        match self.game_status {
+            GameStatus::SplashScreen
+            | GameStatus::Options
+            | GameStatus::Editor
+            | GameStatus::LevelSelect
+            | GameStatus::Stats
+            | GameStatus::Achievements
+            | GameStatus::Attract => MusicContext::Splash,
+            GameStatus::GameOver => MusicContext::GameOver,
+            GameStatus::Playing | GameStatus::Paused | GameStatus::NewLevel => {
+                MusicContext::Level(self.level)
+            }
+        }
+    }
+
+    /// How tense things are, from 0.0 (safely docked) to 1.0 (asteroids
+    /// all around the pod), for the music to follow
+    pub fn music_intensity(&self) -> f32 {
+        if self.game_status != GameStatus::Playing
+            || matches!(
+                self.pod_status,
+                PodStatus::Inactive | PodStatus::ReadyForTakeOff
+            )
+        {
+            return 0.0;
+        }
+        let pod_centre_x = self.pod_pos_x + self.pod_size / 2.0;
+        let pod_centre_y = self.pod_pos_y + self.pod_size / 2.0;
+        let nearby = self
+            .asteroids
+            .iter()
+            .filter(|a| {
+                let x = a.x_pos + a.r1_offset_x + a.r1;
+                let y = a.y_pos + a.r1_offset_y + a.r1;
+                distance(pod_centre_x, pod_centre_y, x, y) < 250.0 * self.size_multiplier
+            })
+            .count();
+        0.4 + 0.6 * (nearby as f32 / 4.0).min(1.0)
+    }
+
+    /// Opens the level editor from the title screen
+    pub fn open_editor(&mut self) {
+        if self.transition(Transition::OpenEditor) {
+            self.edit_level(1);
+        }
+    }
+
+    pub fn close_editor(&mut self) {
+        if self.game_status == GameStatus::Editor {
+            self.editor = None;
+            self.restart();
+        }
+    }
+
+    /// Makes a change to the level being edited, if the editor is open
+    pub fn edit(&mut self, change: impl FnOnce(&mut Editor)) {
+        let Some(mut editor) = self.editor.take() else {
+            return;
+        };
+        change(&mut editor);
+        self.show_edited_level(&editor.level);
+        self.editor = Some(editor);
+    }
+
+    // Lays the level being edited out to be drawn, with the mothership where
+    // it starts
+    fn show_edited_level(&mut self, level: &LevelDefinition) {
+        self.lay_out_level(level);
+        self.mothership_pos_x = 50.0;
+    }
+
+    /// Moves the editor on to another level number
+    pub fn editor_change_level(&mut self, delta: i32) {
+        if let Some(editor) = &self.editor {
+            let number = editor.number.saturating_add_signed(delta).max(1);
+            self.edit_level(number);
+        }
+    }
+
+    fn edit_level(&mut self, number: u32) {
+        let level = self.levels.get(&number).cloned().unwrap_or_default();
+        self.show_edited_level(&level);
+        self.editor = Some(Editor::new(
+            number,
+            level,
+            self.window_width as f32,
+            self.window_height as f32,
+        ));
+    }
+
+    /// Saves the level being edited, returning true if it worked
+    pub fn editor_save(&mut self) -> bool {
+        let Some(editor) = self.editor.as_mut() else {
+            return false;
+        };
+        let dir = Path::new(&self.resource_path).join("levels");
+        match editor.save(&dir) {
+            Ok(()) => {
+                editor.message = "Saved".to_string();
+                self.levels.insert(editor.number, editor.level.clone());
+                true
+            }
+            Err(e) => {
+                editor.message = e;
+                false
+            }
+        }
+    }
+
+    /// Saves the level being edited and starts playing it
+    pub fn editor_play(&mut self) {
+        if !self.editor_save() {
+            return;
+        }
+        let Some(editor) = self.editor.take() else {
+            return;
+        };
+        self.playtesting = true;
+        self.new_game(editor.number);
+        self.transition(Transition::Start);
+    }
+
+    pub fn show_options(&mut self) {
+        self.transition(Transition::OpenOptions);
+    }
+
+    pub fn close_options(&mut self) {
+        if self.game_status == GameStatus::Options {
+            // The first level was made before the difficulty could change
+            self.new_game(1);
+            self.save_profiles();
+            self.transition(Transition::BackToTitle);
+        }
+    }
+
+    pub fn show_stats(&mut self) {
+        self.transition(Transition::OpenStats);
+    }
+
+    pub fn close_stats(&mut self) {
+        if self.game_status == GameStatus::Stats {
+            self.transition(Transition::BackToTitle);
+        }
+    }
+
+    pub fn show_achievements(&mut self) {
+        self.transition(Transition::OpenAchievements);
+    }
+
+    pub fn close_achievements(&mut self) {
+        if self.game_status == GameStatus::Achievements {
+            self.transition(Transition::BackToTitle);
+        }
+    }
+
+    pub fn options_select(&mut self, delta: i32) {
+        self.options_cursor = options::cycle(self.options_cursor, delta, OptionItem::ALL.len());
+    }
+
+    pub fn options_change(&mut self, delta: i32) {
+        match OptionItem::ALL[self.options_cursor] {
+            OptionItem::Profile => {
+                self.profiles.borrow_mut().cycle(delta);
+            }
+            OptionItem::Theme => {
+                self.theme = options::cycle(self.theme, delta, self.themes.len());
+            }
+            OptionItem::Display => {
+                self.speccy_mode = !self.speccy_mode;
+            }
+            OptionItem::MasterVolume => {
+                let volume = &mut self.audio_settings.master;
+                *volume = audio::change_level(*volume, delta);
+            }
+            OptionItem::MusicVolume => {
+                let volume = &mut self.audio_settings.music;
+                *volume = audio::change_level(*volume, delta);
+            }
+            OptionItem::SfxVolume => {
+                let volume = &mut self.audio_settings.sfx;
+                *volume = audio::change_level(*volume, delta);
+            }
+            OptionItem::Difficulty => {
+                self.chosen_difficulty.cycle_preset(delta);
+            }
+            OptionItem::Curve(setting) => {
+                self.chosen_difficulty.change(setting, delta);
+            }
+        }
+    }
+
+    pub fn set_mode(&mut self, mode: GameMode) {
+        self.mode = mode;
+    }
+
+    /// Plays on `difficulty` from the next game on, as if it had been set on
+    /// the options screen
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.chosen_difficulty = difficulty;
+    }
+
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn pods_remaining(&self) -> u32 {
+        self.pods_remaining
+    }
+
+    pub fn men_to_rescue(&self) -> u32 {
+        self.men_to_rescue
+    }
+
+    pub fn pod_status(&self) -> PodStatus {
+        self.pod_status
+    }
+
+    /// Top left of the pod, in window pixels
+    pub fn pod_position(&self) -> (f32, f32) {
+        (self.pod_pos_x, self.pod_pos_y)
+    }
+
+    /// Picks another way to play from the title screen
+    pub fn change_mode(&mut self, delta: i32) {
+        if self.game_status == GameStatus::SplashScreen {
+            self.mode = self.mode.cycle(delta);
+            self.today = daily::today();
+            self.new_game(1);
+        }
+    }
+
+    /// Enter, on any screen that waits for it. The game over screen waits for
+    /// Y or N instead.
+    pub fn start(&mut self) {
+        match self.game_status {
+            GameStatus::SplashScreen
+                if self.mode == GameMode::Practice
+                    || (self.mode == GameMode::Classic
+                        && self.profiles.borrow().current().unlocked_levels() > 1) =>
+            {
+                if self.mode == GameMode::Classic {
+                    // Carry on from the furthest level reached
+                    self.selected_level = u32::MAX;
+                }
+                self.selected_level = self
+                    .selected_level
+                    .clamp(1, self.profiles.borrow().current().unlocked_levels());
+                self.transition(Transition::ChooseLevel);
+            }
+            GameStatus::SplashScreen => {
+                self.transition(Transition::Start);
+            }
+            GameStatus::LevelSelect => {
+                self.new_game(self.selected_level);
+                self.transition(Transition::Start);
+            }
+            GameStatus::NewLevel => {
+                self.transition(Transition::Continue);
+            }
+            GameStatus::Paused => {
+                self.transition(Transition::Resume);
+            }
+            GameStatus::Options => self.close_options(),
+            GameStatus::Stats => self.close_stats(),
+            GameStatus::Achievements => self.close_achievements(),
+            _ => {}
+        }
+    }
+
+    /// Starts the game set up on the title screen straight away, without
+    /// choosing a level, for bots and agents
+    pub fn play(&mut self) {
+        self.transition(Transition::Start);
+    }
+
+    /// P, or the window losing focus
+    pub fn pause(&mut self) {
+        self.transition(Transition::Pause);
+    }
+
+    pub fn game_status(&self) -> GameStatus {
+        self.game_status
+    }
+
+    // Moves to another screen if the transition can happen from this one,
+    // returning whether it did
+    fn transition(&mut self, transition: Transition) -> bool {
+        let Some(to) = transition.from(self.game_status) else {
+            return false;
+        };
+        self.status_changes.push(StatusChange {
+            transition,
+            from: self.game_status,
+            to,
+        });
+        self.game_status = to;
+        if matches!(
+            transition,
+            Transition::Start | Transition::RestartLevel | Transition::PlayDemo
+        ) {
+            let on_record = self.mode != GameMode::Practice && self.on_record();
+            self.emit(EventKind::GameStarted {
+                level: self.level,
+                on_record,
+            });
+            self.start_daily();
+        }
+        true
+    }
+
+    // Changes what the pod is doing in one of the ways
+    // `state::pod_can_change` allows, returning whether it did
+    fn set_pod_status(&mut self, status: PodStatus) -> bool {
+        if !state::pod_can_change(self.pod_status, status) {
+            eprintln!(
+                "Ignoring pod change from {:?} to {:?}",
+                self.pod_status, status
+            );
+            return false;
+        }
+        self.pod_status = status;
+        true
+    }
+
+    // Changes what the terran is doing in one of the ways
+    // `state::man_can_change` allows, returning whether it did
+    fn set_man_status(&mut self, status: ManStatus) -> bool {
+        if !state::man_can_change(self.man_status, status) {
+            eprintln!(
+                "Ignoring terran change from {:?} to {:?}",
+                self.man_status, status
+            );
+            return false;
+        }
+        self.man_status = status;
+        true
+    }
+
+    pub fn close_level_select(&mut self) {
+        if self.game_status == GameStatus::LevelSelect {
+            self.transition(Transition::BackToTitle);
+        }
+    }
+
+    /// Moves through the unlocked levels
+    pub fn select_level(&mut self, delta: i32) {
+        let unlocked = self.profiles.borrow().current().unlocked_levels();
+        self.selected_level = self
+            .selected_level
+            .saturating_add_signed(delta)
+            .clamp(1, unlocked);
+    }
+
+    /// Stops or starts the asteroids, while practising
+    pub fn toggle_freeze(&mut self) {
+        if self.mode == GameMode::Practice {
+            self.frozen = !self.frozen;
+        }
+    }
+
+    /// Starts the practice level again straight away
+    pub fn restart_level(&mut self) {
+        if self.mode == GameMode::Practice && self.game_status == GameStatus::Playing {
+            self.new_game(self.level);
+            self.transition(Transition::RestartLevel);
+        }
+    }
+
+    /// Starts a game at `level`, laid out as `layout` if there is one, for
+    /// scripted tests
+    pub fn play_level(&mut self, level: u32, layout: Option<&LevelDefinition>) {
+        self.new_game(level);
+        if let Some(layout) = layout {
+            self.pods_remaining = 0;
+            self.load_level(layout);
+            self.level_par = timeattack::par_frames(self.men_to_rescue);
+        }
+        self.transition(Transition::Start);
+    }
+
+    /// Moves things to where a scripted test wants them
+    pub fn set_up(&mut self, setup: &Setup) {
+        if let Some(status) = setup.pod_status {
+            self.pod_status = status;
+        }
+        self.pod_pos_x = setup.pod_x.unwrap_or(self.pod_pos_x);
+        self.pod_pos_y = setup.pod_y.unwrap_or(self.pod_pos_y);
+        self.mothership_pos_x = setup.mothership_x.unwrap_or(self.mothership_pos_x);
+        self.pods_remaining = setup.pods_remaining.unwrap_or(self.pods_remaining);
+        self.men_to_rescue = setup.men_to_rescue.unwrap_or(self.men_to_rescue);
+    }
+
+    /// Reads the profiles from `path` and saves them there as players get
+    /// further
+    pub fn load_profiles(&mut self, path: &Path) {
+        *self.profiles.borrow_mut() = Profiles::load(path);
+    }
+
+    /// The players' profiles, shared so their records can be kept as the
+    /// game's events are published
+    pub fn profiles(&self) -> Rc<RefCell<Profiles>> {
+        self.profiles.clone()
+    }
+
+    /// Plays as someone else from now on
+    pub fn select_profile(&mut self, name: &str) {
+        self.profiles.borrow_mut().select(name);
+        self.save_profiles();
+    }
+
+    fn save_profiles(&self) {
+        if let Err(e) = self.profiles.borrow().save() {
+            eprintln!("Couldn't save profiles: {}", e);
+        }
+    }
+
+    /// Reads the high scores from `path` and saves them there at the end of
+    /// each game
+    pub fn load_high_scores(&mut self, path: &Path) {
+        self.high_scores = HighScores::load(path);
+    }
+
+    pub fn restart(&mut self) {
+        self.quit();
+        self.today = daily::today();
+        self.playtesting = false;
+        self.new_game(1);
+        self.transition(Transition::BackToTitle);
+    }
+
+    /// Gives up on the game being played, if there is one, so what was done
+    /// in it is still kept
+    pub fn quit(&mut self) {
+        if matches!(
+            self.game_status,
+            GameStatus::Playing | GameStatus::Paused | GameStatus::NewLevel
+        ) {
+            self.emit(EventKind::GameQuit { level: self.level });
+        }
+    }
+
+    // Starts from the given level with no pods or points from before
+    fn new_game(&mut self, level: u32) {
+        self.pods_remaining = 0;
+        self.score = 0;
+        self.high_score_rank = None;
+        self.level_times.clear();
+        self.out_of_time = false;
+        self.best_run_rank = None;
+        self.tick = 0;
+        self.pod_status = PodStatus::Inactive;
+        // Generated levels depend on where the mothership is, so every game
+        // starts with it in the same place
+        self.mothership_pos_x = 50.0;
+        self.mothership_direction = self.mothership_direction.abs();
+        if level > 1 && self.mode != GameMode::Practice {
+            self.pods_remaining = CONTINUE_PODS;
+        }
+        self.difficulty = self
+            .mode
+            .fixed_difficulty()
+            .unwrap_or(self.chosen_difficulty);
+        match self.mode {
+            GameMode::TimeAttack => self.set_seed(timeattack::SEED),
+            GameMode::Daily => {
+                self.set_seed(daily::seed(self.today));
+                self.daily_scored = self.high_scores.daily_result(self.today).is_none();
+            }
+            _ => {}
+        }
+        self.new_level(level);
+    }
 
     pub fn next_frame(&mut self) {
+        self.attract_frame();
+        if !self.toasts.is_empty() {
+            self.toast_frames += 1;
+            if self.toast_frames >= TOAST_SECONDS * FRAMES_PER_SECOND {
+                self.toasts.pop_front();
+                self.toast_frames = 0;
+            }
+        }
         if self.game_status != GameStatus::Playing {
             return;
         }
-        if (self.mothership_pos_x > (self.window_width - (self.mothership_width + 50)) as f32
-            && self.mothership_direction > 0)
-            || (self.mothership_pos_x < 50.0 && self.mothership_direction < 0)
+        self.level_frames += 1;
+        self.tick += 1;
+        if self.mode == GameMode::TimeAttack
+            && self.level_frames >= timeattack::limit_frames(self.level_par)
         {
-            self.mothership_direction = -self.mothership_direction;
+            self.out_of_time = true;
+            self.game_over();
+            return;
         }
-        self.mothership_pos_x += self.mothership_direction as f32 * self.size_multiplier;
-        for asteroid in &mut self.asteroids {
-            asteroid.x_pos += asteroid.speed;
-            if asteroid.speed > 0.0 && asteroid.x_pos > self.window_width as f32 {
-                asteroid.x_pos = -150.0 * self.size_multiplier;
-            }
-            if asteroid.speed < 0.0 && asteroid.x_pos < -150.0 * self.size_multiplier {
-                asteroid.x_pos = self.window_width as f32;
-            }
+        (self.mothership_pos_x, self.mothership_direction) = move_mothership(
+            self.mothership_pos_x,
+            self.mothership_direction,
+            self.window_width as f32,
+            self.mothership_width as f32,
+            self.size_multiplier,
+        );
+        let frozen = self.frozen && self.mode == GameMode::Practice;
+        for asteroid in self.asteroids.iter_mut().filter(|_| !frozen) {
+            asteroid.x_pos = move_asteroid(
+                asteroid.x_pos,
+                asteroid.speed,
+                self.window_width as f32,
+                self.size_multiplier,
+            );
         }
         if self.pod_status == PodStatus::Dropping && !self.check_for_pod_landing() {
             match self.check_for_pod_collision() {
                 CollisionType::Fatal => {
-                    self.explode_pod();
+                    self.explode_pod(Cause::Asteroid);
                 }
-                CollisionType::NearMiss => {
-                    self.sounds_to_play.push(Sounds::Scrape);
+                CollisionType::NearMiss(closeness) => {
+                    self.scrape(closeness);
                 }
-                _ => {}
+                CollisionType::None => self.scraping = false,
             }
             self.pod_pos_y += 5.0 * self.size_multiplier;
         }
         if self.pod_status == PodStatus::Ascending && !self.check_for_pod_docking() {
             match self.check_for_pod_collision() {
                 CollisionType::Fatal => {
-                    self.explode_pod();
+                    self.explode_pod(Cause::Asteroid);
                 }
-                CollisionType::NearMiss => {
-                    self.sounds_to_play.push(Sounds::Scrape);
+                CollisionType::NearMiss(closeness) => {
+                    self.scrape(closeness);
                 }
-                _ => {}
+                CollisionType::None => self.scraping = false,
             }
             self.pod_pos_y -= 5.0 * self.size_multiplier;
         }
@@ -596,31 +2374,221 @@ impl Game {
             }
             self.check_for_pod_docking();
         }
+        if self.pod_status == PodStatus::Exploding {
+            self.pod_explosion_timer += 1;
+            if self.pod_explosion_timer > 20 {
+                self.set_pod_status(PodStatus::Inactive);
+            }
+        }
         if self.man_status == ManStatus::EnteringPod {
+            // The moonbase can be either side of the landing pad
+            let step = 10.0 * self.size_multiplier;
             if self.man_pos_x > self.pod_pos_x + 15.0 {
-                self.man_pos_x -= 10.0 * self.size_multiplier;
+                self.man_pos_x -= step;
+            } else if self.man_pos_x < self.pod_pos_x + 15.0 - step {
+                self.man_pos_x += step;
             } else {
-                self.man_status = ManStatus::Inactive;
-                self.sounds_to_play.push(Sounds::Seatbelt);
-                self.man_pos_x = self.window_width as f32 * 0.75;
+                self.set_man_status(ManStatus::Inactive);
+                self.emit(EventKind::RescueeBoarded);
+                self.man_pos_x = self.moonbase_x;
                 self.man_pos_y = self.window_height as f32 - 60.0 * self.size_multiplier;
             }
         }
     }
 
-    fn explode_pod(&mut self) {
-        self.pod_status = PodStatus::Exploding;
-        self.sounds_to_play.push(Sounds::Explosion);
-        if !self.debugging_aids {
-            self.pods_remaining -= 1;
+    // Counts how long the title screen has been left alone, and moves attract
+    // mode on from one stage to the next
+    fn attract_frame(&mut self) {
+        let Some(stage) = self.attract else {
+            if self.game_status == GameStatus::SplashScreen {
+                self.idle_frames += 1;
+                if self.idle_frames >= attract::IDLE_SECONDS * FRAMES_PER_SECOND {
+                    self.show_attract(Stage::Demo);
+                }
+            } else {
+                self.idle_frames = 0;
+            }
+            return;
+        };
+        self.attract_frames += 1;
+        // The demo is over once its level is finished or its pods run out
+        let demo_over = stage == Stage::Demo && self.game_status != GameStatus::Playing;
+        if demo_over || self.attract_frames >= stage.seconds() * FRAMES_PER_SECOND {
+            match stage.next() {
+                Some(next) => self.show_attract(next),
+                None => self.stop_attract(),
+            }
+            return;
+        }
+        if let Some(mut pilot) = self.demo_pilot.take() {
+            pilot.fly(self);
+            self.demo_pilot = Some(pilot);
+        }
+    }
+
+    fn show_attract(&mut self, stage: Stage) {
+        self.attract = Some(stage);
+        self.attract_frames = 0;
+        self.demo_pilot = None;
+        if stage == Stage::Demo {
+            let level = self.rng.gen_range(1..=attract::DEMO_LEVELS);
+            self.new_game(level);
+            self.transition(Transition::PlayDemo);
+            self.demo_pilot = Some(Bot::new(attract::DEMO_SKILL));
+        } else {
+            self.transition(Transition::ShowAttract);
+        }
+    }
+
+    pub fn in_attract_mode(&self) -> bool {
+        self.attract.is_some()
+    }
+
+    /// Goes back to the title screen from attract mode, and starts waiting
+    /// for the title screen to be left alone again
+    pub fn stop_attract(&mut self) {
+        if self.attract.take().is_some() {
+            self.demo_pilot = None;
+            self.restart();
+        }
+        self.idle_frames = 0;
+    }
+
+    fn play_sound_at(&mut self, sound: Sounds, x: f32, intensity: f32, pitch: f32) {
+        self.sounds_to_play.push(SoundEvent {
+            sound,
+            pan: audio::pan(x, self.window_width as f32),
+            intensity,
+            pitch,
+        });
+    }
+
+    // Whether how this game goes is kept: not for attract mode's demos,
+    // levels tried out from the editor or games the autopilot flies
+    fn on_record(&self) -> bool {
+        self.attract.is_none() && !self.playtesting && !self.autopiloted
+    }
+
+    // Records something that happened to the pod
+    fn emit(&mut self, kind: EventKind) {
+        self.events.push(Event {
+            tick: self.tick,
+            x: self.pod_pos_x + self.pod_size / 2.0,
+            y: self.pod_pos_y + self.pod_size / 2.0,
+            kind,
+        });
+    }
+
+    /// Shows the player an achievement they've just unlocked
+    pub fn announce(&mut self, achievement: Achievement) {
+        self.toasts.push_back(achievement);
+        self.emit(EventKind::AchievementUnlocked { achievement });
+    }
+
+    fn scrape(&mut self, closeness: f32) {
+        // Scraping past an asteroid takes a few frames, but it's only one
+        // near miss
+        if !self.scraping {
+            self.scraping = true;
+            self.emit(EventKind::NearMiss { closeness });
+        }
+        // the closer the shave, the higher the scrape
+        let pitch = 0.8 + 0.6 * closeness.clamp(0.0, 1.0);
+        self.play_sound_at(
+            Sounds::Scrape,
+            self.pod_pos_x + self.pod_size / 2.0,
+            1.0,
+            pitch,
+        );
+    }
+
+    fn explode_pod(&mut self, cause: Cause) {
+        let ascending = self.pod_status == PodStatus::Ascending;
+        let from_mothership = distance(
+            self.pod_pos_x,
+            self.pod_pos_y,
+            self.mothership_pos_x,
+            self.mothership_pos_y,
+        ) / self.window_height as f32;
+        self.emit(EventKind::PodDestroyed {
+            cause,
+            ascending,
+            from_mothership,
+        });
+        self.set_pod_status(PodStatus::Exploding);
+        self.pod_explosion_timer = 0;
+        self.level_crashes += 1;
+        if !self.debugging_aids && self.mode != GameMode::Practice {
+            // Set-up levels can start with none at all
+            self.pods_remaining = self.pods_remaining.saturating_sub(1);
             if self.pods_remaining == 0 {
                 self.game_over();
             }
         }
     }
 
+    // The day's go is used up as soon as it starts, so quitting part way
+    // through doesn't earn another
+    fn start_daily(&mut self) {
+        if self.mode != GameMode::Daily || !self.daily_scored || !self.on_record() {
+            return;
+        }
+        self.high_scores.add_daily(DailyResult {
+            day: self.today,
+            score: 0,
+            level: self.level,
+        });
+        if let Err(e) = self.high_scores.save() {
+            eprintln!("Couldn't save high scores: {}", e);
+        }
+    }
+
     fn game_over(&mut self) {
-        self.game_status = GameStatus::GameOver;
+        if !self.transition(Transition::GameOver) {
+            return;
+        }
+        self.emit(EventKind::GameOver {
+            level: self.level,
+            score: self.score,
+        });
+        // Nothing from practice, a demo, a playtest or the autopilot counts
+        if self.mode == GameMode::Practice || !self.on_record() {
+            return;
+        }
+        if self.mode == GameMode::Daily {
+            // Only the first go each day counts, and it goes in the history
+            // rather than the high scores
+            if self.daily_scored {
+                self.high_scores.update_daily(DailyResult {
+                    day: self.today,
+                    score: self.score,
+                    level: self.level,
+                });
+                if let Err(e) = self.high_scores.save() {
+                    eprintln!("Couldn't save high scores: {}", e);
+                }
+            }
+            return;
+        }
+        self.high_score_rank = self.high_scores.add(Entry {
+            score: self.score,
+            level: self.level,
+            mode: self.mode,
+            difficulty: self.difficulty,
+        });
+        if self.mode == GameMode::TimeAttack && self.level_times.len() as u32 == timeattack::LEVELS
+        {
+            self.best_run_rank = self.high_scores.add_run(Run {
+                frames: self.level_times.iter().map(|t| t.frames).sum(),
+                difficulty: self.difficulty,
+                levels: self.level_times.clone(),
+            });
+        }
+        if self.high_score_rank.is_some() || self.best_run_rank.is_some() {
+            if let Err(e) = self.high_scores.save() {
+                eprintln!("Couldn't save high scores: {}", e);
+            }
+        }
     }
 
     fn check_for_pod_landing(&mut self) -> bool {
@@ -636,14 +2604,14 @@ impl Game {
                 - self.ground_height
                 - self.landing_pad_height
                 - self.pod_size;
-            self.pod_status = PodStatus::ReadyForTakeOff;
-            self.sounds_to_play.push(Sounds::Landed);
-            self.man_status = ManStatus::EnteringPod;
+            self.set_pod_status(PodStatus::ReadyForTakeOff);
+            self.emit(EventKind::Landed);
+            self.set_man_status(ManStatus::EnteringPod);
             return true;
         }
         if self.pod_pos_y >= self.window_height as f32 - self.ground_height - self.pod_size {
             self.pod_pos_y = self.window_height as f32 - self.ground_height - self.pod_size;
-            self.explode_pod();
+            self.explode_pod(Cause::Ground);
             return true;
         }
         false
@@ -655,116 +2623,400 @@ impl Game {
                 && self.pod_pos_x
                     <= self.mothership_pos_x + self.mothership_width as f32 - self.pod_size
             {
+                if self.pod_status == PodStatus::AutoDock {
+                    self.level_autodocks += 1;
+                }
+                // Endless games can go on long enough for this to add up
+                self.score = self
+                    .score
+                    .saturating_add(RESCUE_POINTS.saturating_mul(self.level));
                 if self.pod_status != PodStatus::AutoDock {
+                    self.score = self.score.saturating_add(DOCKING_POINTS);
+                }
+                if self.pod_status != PodStatus::AutoDock
+                    && self.rules.docking_bonus
+                    && self.difficulty.docking_bonus
+                {
                     // bonus for not needing autodock
                     self.pods_remaining += 1;
-                    self.sounds_to_play.push(Sounds::Bonus);
+                    self.emit(EventKind::BonusAwarded { pods: 1, points: 0 });
                 }
-                self.sounds_to_play.push(Sounds::Docked);
-                self.pod_status = PodStatus::Inactive;
-                self.men_to_rescue -= 1;
+                self.emit(EventKind::Docked {
+                    autodock: self.pod_status == PodStatus::AutoDock,
+                });
+                self.set_pod_status(PodStatus::Inactive);
+                self.men_to_rescue = self.men_to_rescue.saturating_sub(1);
                 if self.men_to_rescue == 0 {
-                    self.new_level(self.level + 1);
+                    self.level_complete();
                 }
                 return true;
+            } else if self.rules.autodock {
+                if self.pod_status != PodStatus::AutoDock {
+                    self.set_pod_status(PodStatus::AutoDock);
+                }
             } else {
-                self.pod_status = PodStatus::AutoDock;
+                self.explode_pod(Cause::Mothership);
+                return true;
             }
         }
         false
     }
 
+    fn level_complete(&mut self) {
+        self.emit(EventKind::LevelComplete {
+            level: self.level,
+            carry_over_pods: self.rules.carry_over_pods,
+        });
+        if self.mode.unlocks_levels() && self.on_record() {
+            let stars = Stars {
+                no_crashes: self.level_crashes == 0,
+                no_autodock: self.level_autodocks == 0,
+                under_par: self.level_frames < self.level_par,
+            };
+            self.profiles
+                .borrow_mut()
+                .current_mut()
+                .add_stars(self.level, stars);
+            self.save_profiles();
+        }
+        if self.mode == GameMode::TimeAttack {
+            let time = LevelTime {
+                level: self.level,
+                frames: self.level_frames,
+                par: self.level_par,
+            };
+            self.level_times.push(time);
+            if time.under_par() {
+                self.pods_remaining += timeattack::BONUS_PODS;
+                self.score = self.score.saturating_add(timeattack::BONUS_POINTS);
+                self.emit(EventKind::BonusAwarded {
+                    pods: timeattack::BONUS_PODS,
+                    points: timeattack::BONUS_POINTS,
+                });
+            }
+            if self.level_times.len() as u32 == timeattack::LEVELS {
+                self.game_over();
+                return;
+            }
+        }
+        self.transition(Transition::NextLevel);
+        self.new_level(self.level.saturating_add(1));
+    }
+
     fn check_for_pod_collision(&mut self) -> CollisionType {
         let pod_centre_x = self.pod_pos_x + (self.pod_size / 2.0);
         let pod_centre_y = self.pod_pos_y + (self.pod_size / 2.0);
+        let hit_scale = self.difficulty.hit_scale();
         for (idx, asteroid) in self.asteroids.iter().enumerate() {
             let blob1_centre_x = asteroid.x_pos + asteroid.r1_offset_x + asteroid.r1;
             let blob1_centre_y = asteroid.y_pos + asteroid.r1_offset_y + asteroid.r1;
             let mut dist = distance(pod_centre_x, pod_centre_y, blob1_centre_x, blob1_centre_y);
-            if dist <= asteroid.r1 {
+            if dist <= asteroid.r1 * hit_scale {
                 self.asteroids.remove(idx);
                 return CollisionType::Fatal;
             }
             if dist <= asteroid.r1 + self.pod_size / 2.0 {
-                return CollisionType::NearMiss;
+                return CollisionType::NearMiss(1.0 - (dist - asteroid.r1) / (self.pod_size / 2.0));
             }
             let blob2_centre_x = asteroid.x_pos + asteroid.r2_offset_x + asteroid.r2;
             let blob2_centre_y = asteroid.y_pos + asteroid.r2_offset_y + asteroid.r2;
             dist = distance(pod_centre_x, pod_centre_y, blob2_centre_x, blob2_centre_y);
-            if dist <= asteroid.r2 {
+            if dist <= asteroid.r2 * hit_scale {
                 self.asteroids.remove(idx);
                 return CollisionType::Fatal;
             }
             if dist <= asteroid.r2 + self.pod_size / 2.0 {
-                return CollisionType::NearMiss;
+                return CollisionType::NearMiss(1.0 - (dist - asteroid.r2) / (self.pod_size / 2.0));
             }
             let blob3_centre_x = asteroid.x_pos + asteroid.r3_offset_x + asteroid.r3;
             let blob3_centre_y = asteroid.y_pos + asteroid.r3_offset_y + asteroid.r3;
             dist = distance(pod_centre_x, pod_centre_y, blob3_centre_x, blob3_centre_y);
-            if dist <= asteroid.r3 {
+            if dist <= asteroid.r3 * hit_scale {
                 self.asteroids.remove(idx);
                 return CollisionType::Fatal;
             }
             if dist <= asteroid.r3 + self.pod_size / 2.0 {
-                return CollisionType::NearMiss;
+                return CollisionType::NearMiss(1.0 - (dist - asteroid.r3) / (self.pod_size / 2.0));
             }
         }
         CollisionType::None
     }
 
-    pub fn new_level(&mut self, level: u8) {
-        if level > 1 {
-            self.game_status = GameStatus::NewLevel;
-        }
+    /// Sets up a level from its file in `res/levels`, or makes one up if
+    /// there isn't a file for it
+    pub fn new_level(&mut self, level: u32) {
         self.level = level;
+        if self.mode.unlocks_levels()
+            && self.on_record()
+            && self.profiles.borrow_mut().current_mut().reach(level)
+        {
+            self.save_profiles();
+        }
+        self.level_crashes = 0;
+        self.level_autodocks = 0;
+        // A terran still on their way to the pod goes back to the moonbase
+        if self.man_status == ManStatus::EnteringPod {
+            self.set_man_status(ManStatus::Inactive);
+        }
         self.asteroids.clear();
-        let num_asteroids = 16 + 2 * level;
+        self.pods_carried_over = self.pods_remaining;
+        let definition = if self.mode.uses_level_files() {
+            self.levels.get(&level).cloned()
+        } else {
+            None
+        };
+        match definition {
+            Some(definition) => self.load_level(&definition),
+            None => self.generate_level(level),
+        }
+        self.level_frames = 0;
+        self.level_par = timeattack::par_frames(self.men_to_rescue);
         let mut rng = rand::thread_rng();
-        self.men_to_rescue = (level + 1) as u32;
-        if self.men_to_rescue > 5 {
-            self.men_to_rescue = 5;
+        if self.stars.is_empty() {
+            for _ in 0..320 {
+                self.stars.push(Star {
+                    y_pos: rng
+                        .gen_range((self.window_height as f32 * 0.075) as u32..self.window_height),
+                    x_pos: rng.gen_range(0..self.window_width),
+                    radius: rng.gen_range(
+                        (2.0 * self.size_multiplier) as u8..(5.0 * self.size_multiplier) as u8,
+                    ),
+                    luminosity: rng.gen_range(64..128),
+                });
+            }
         }
-        self.pods_carried_over = self.pods_remaining;
-        self.pods_remaining += 1 + (self.men_to_rescue as f32 * 0.25) as u32;
+    }
+
+    fn load_level(&mut self, definition: &LevelDefinition) {
+        self.level_name = definition.name.clone();
+        self.rules = definition.rules;
+        self.men_to_rescue = definition.men_to_rescue;
+        if !definition.rules.carry_over_pods {
+            self.pods_carried_over = 0;
+            self.pods_remaining = 0;
+        }
+        self.pods_remaining += self.difficulty.pods(definition.pods);
+        self.lay_out_level(definition);
+        // Hand-made levels keep their asteroids but not their speeds and sizes
+        self.mothership_direction = self.difficulty.mothership_speed(self.mothership_direction);
+        for asteroid in &mut self.asteroids {
+            asteroid.speed = self.difficulty.scale(Setting::Speed, asteroid.speed);
+            asteroid.scale_blobs(self.difficulty.scale(Setting::Size, 1.0));
+        }
+    }
+
+    // Puts everything where the level says, without starting it
+    fn lay_out_level(&mut self, definition: &LevelDefinition) {
+        self.mothership_direction = definition.mothership_speed;
+        self.moonbase_x = self.window_width as f32 * definition.moonbase;
+        self.man_pos_x = self.moonbase_x;
+        self.landing_pad_width = definition.landing_pad.width * self.size_multiplier;
+        self.landing_pad_x = (self.window_width as f32 * definition.landing_pad.x
+            - self.landing_pad_width / 2.0)
+            .clamp(
+                0.0,
+                (self.window_width as f32 - self.landing_pad_width).max(0.0),
+            );
+        let m = self.size_multiplier;
+        self.asteroids.clear();
+        for row in &definition.asteroids {
+            self.asteroids.push(Asteroid {
+                y_pos: self.window_height as f32 * row.y,
+                x_pos: self.window_width as f32 * row.x,
+                speed: row.speed * m,
+                r1: row.radii[0] * m,
+                r1_offset_x: row.offsets_x[0] * m,
+                r1_offset_y: row.offsets[0] * m,
+                r2: row.radii[1] * m,
+                r2_offset_x: row.offsets_x[1] * m,
+                r2_offset_y: row.offsets[1] * m,
+                r3: row.radii[2] * m,
+                r3_offset_x: row.offsets_x[2] * m,
+                r3_offset_y: row.offsets[2] * m,
+            });
+        }
+    }
+
+    // The original levels, made up from the level number
+    fn generate_level(&mut self, level: u32) {
+        self.level_name = String::new();
+        self.rules = Rules::default();
+        if self.seeded {
+            // Which fields can be got through depends on where the
+            // mothership is, so it starts every seeded level in the same place
+            self.mothership_pos_x = 50.0;
+            self.mothership_direction = 1;
+        }
+        self.mothership_direction = self
+            .difficulty
+            .mothership_speed(10 * self.mothership_direction.signum());
+        self.moonbase_x = self.window_width as f32 * 0.75;
+        self.man_pos_x = self.moonbase_x;
+        self.landing_pad_width = 250.0 * self.size_multiplier;
+        self.landing_pad_x = self.window_width as f32 / 2.0 - (self.landing_pad_width / 2.0);
+        self.men_to_rescue = mode::plan(self.mode, level).men_to_rescue;
+        self.pods_remaining += self
+            .difficulty
+            .pods(1 + (self.men_to_rescue as f32 * 0.25) as u32);
+        self.generate_asteroid_field(level, SOLVE_SECONDS);
+    }
+
+    /// Makes up asteroids for a level until there is a way through them,
+    /// returning whether each attempt could be solved
+    pub fn generate_asteroid_field(&mut self, level: u32, seconds: f32) -> Vec<bool> {
+        let mut attempts = Vec::new();
+        while attempts.len() < MAX_REROLLS {
+            self.generate_asteroids(level);
+            let solvable = solver::check(&self.field(), seconds).is_solvable();
+            attempts.push(solvable);
+            if solvable {
+                break;
+            }
+        }
+        attempts
+    }
+
+    fn generate_asteroids(&mut self, level: u32) {
+        self.asteroids.clear();
+        let plan = mode::plan(self.mode, level);
+        let num_asteroids = self
+            .difficulty
+            .scale(Setting::Asteroids, plan.asteroids as f32)
+            .round() as u32;
+        let size = self.difficulty.scale(Setting::Size, self.size_multiplier);
+        let rng = &mut self.rng;
         let asteroid_min_y = self.window_height as f32 * 0.144;
         let asteroid_max_y = self.window_height as f32 * 0.7;
         let asteroid_vertical_spacing = (asteroid_max_y - asteroid_min_y) / num_asteroids as f32;
         for n in 0..num_asteroids {
-            let max_speed = 3.0 + (level as f32) / 2.0;
+            let max_speed = self.difficulty.scale(Setting::Speed, plan.max_speed);
             let mut speed = rng.gen_range(-max_speed..max_speed);
             if speed > -0.25 && speed < 0.25 {
                 speed = 0.25 * speed.signum();
             }
             speed *= self.size_multiplier;
-            let asteroid = Asteroid {
+            let mut asteroid = Asteroid {
                 y_pos: asteroid_min_y + asteroid_vertical_spacing * n as f32,
                 x_pos: rng.gen_range(50.0..self.window_width as f32 - 50.0),
                 speed,
-                r1: rng.gen_range(20.0 * self.size_multiplier..40.0 * self.size_multiplier),
+                r1: rng.gen_range(20.0 * size..40.0 * size),
                 r1_offset_x: 0.0 * self.size_multiplier, // yes I know
                 r1_offset_y: rng.gen_range(0.0..30.00 * self.size_multiplier),
-                r2: rng.gen_range(30.0 * self.size_multiplier..50.0 * self.size_multiplier),
+                r2: rng.gen_range(30.0 * size..50.0 * size),
                 r2_offset_x: 20.0 * self.size_multiplier,
                 r2_offset_y: rng.gen_range(0.0..10.00 * self.size_multiplier),
-                r3: rng.gen_range(20.0 * self.size_multiplier..40.0 * self.size_multiplier),
+                r3: rng.gen_range(20.0 * size..40.0 * size),
                 r3_offset_x: 60.0 * self.size_multiplier,
                 r3_offset_y: rng.gen_range(0.0..30.00 * self.size_multiplier),
             };
+            match plan.hazard(rng.gen()) {
+                Hazard::Asteroid => {}
+                Hazard::Streaker => {
+                    asteroid.speed =
+                        STREAKER_SPEED * max_speed * self.size_multiplier * speed.signum();
+                    asteroid.scale_blobs(0.5);
+                }
+                Hazard::Giant => {
+                    asteroid.speed *= 0.4;
+                    asteroid.scale_blobs(1.6);
+                }
+                Hazard::Pair => {
+                    let mut partner = asteroid.clone();
+                    partner.x_pos += PAIR_GAP * self.size_multiplier;
+                    self.asteroids.push(partner);
+                }
+            }
             self.asteroids.push(asteroid);
         }
-        if self.stars.is_empty() {
-            for _ in 0..320 {
-                self.stars.push(Star {
-                    y_pos: rng
-                        .gen_range((self.window_height as f32 * 0.075) as u32..self.window_height),
-                    x_pos: rng.gen_range(0..self.window_width),
-                    radius: rng.gen_range(
-                        (2.0 * self.size_multiplier) as u8..(5.0 * self.size_multiplier) as u8,
-                    ),
-                    luminosity: rng.gen_range(64..128),
-                });
-            }
+    }
+
+    /// Notes that a bot is flying the pods, so nothing it does goes on the
+    /// player's record
+    pub fn set_autopiloted(&mut self, autopiloted: bool) {
+        self.autopiloted = autopiloted;
+    }
+
+    /// Makes levels come out the same each time for the same seed
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.seeded = true;
+    }
+
+    /// The level as it stands, for working out whether it can be finished
+    pub fn field(&self) -> Field {
+        Field {
+            width: self.window_width as f32,
+            size_multiplier: self.size_multiplier,
+            rocks: self
+                .asteroids
+                .iter()
+                .map(|a| Rock {
+                    x: a.x_pos,
+                    y: a.y_pos,
+                    speed: a.speed,
+                    blobs: [
+                        (a.r1_offset_x, a.r1_offset_y, a.r1),
+                        (a.r2_offset_x, a.r2_offset_y, a.r2),
+                        (a.r3_offset_x, a.r3_offset_y, a.r3),
+                    ],
+                })
+                .collect(),
+            mothership_x: self.mothership_pos_x,
+            mothership_y: self.mothership_pos_y,
+            mothership_direction: self.mothership_direction,
+            mothership_width: self.mothership_width as f32,
+            pod_size: self.pod_size,
+            landing_pad_x: self.landing_pad_x,
+            landing_pad_width: self.landing_pad_width,
+            landing_y: self.window_height as f32
+                - self.ground_height
+                - self.landing_pad_height
+                - self.pod_size,
+            moonbase_x: self.moonbase_x,
+            autodock: self.rules.autodock,
+            hit_scale: self.difficulty.hit_scale(),
+        }
+    }
+
+    /// What's wrong with the game as it stands, if anything, for fuzzing and
+    /// property tests. Counters that wrap panic in tests before this sees them.
+    pub fn check_invariants(&self) -> Result<(), String> {
+        let in_a_level = matches!(self.game_status, GameStatus::Playing | GameStatus::Paused);
+        if in_a_level && self.men_to_rescue == 0 {
+            return Err("playing with nobody left to rescue".to_string());
+        }
+        if in_a_level
+            && self.pods_remaining == 0
+            && self.mode != GameMode::Practice
+            && !self.debugging_aids
+        {
+            return Err("playing with no pods left".to_string());
         }
+        let flying = matches!(
+            self.pod_status,
+            PodStatus::Dropping | PodStatus::Ascending | PodStatus::ReadyForTakeOff
+        );
+        let ground = self.window_height as f32 - self.ground_height - self.pod_size;
+        if flying
+            && (self.pod_pos_x < 0.0
+                || self.pod_pos_x > self.window_width as f32 - self.pod_size
+                || self.pod_pos_y < self.mothership_pos_y
+                || self.pod_pos_y > ground)
+        {
+            return Err(format!(
+                "{:?} pod off the screen at ({}, {})",
+                self.pod_status, self.pod_pos_x, self.pod_pos_y
+            ));
+        }
+        if self.man_status == ManStatus::EnteringPod
+            && self.pod_status != PodStatus::ReadyForTakeOff
+        {
+            return Err(format!(
+                "terran getting into a pod that is {:?}",
+                self.pod_status
+            ));
+        }
+        Ok(())
     }
 
     pub fn drop_pod(&mut self) {
@@ -774,10 +3026,11 @@ impl Game {
         if self.pod_status != PodStatus::Inactive && self.pod_status != PodStatus::Exploding {
             return;
         };
-        self.pod_status = PodStatus::Dropping;
-        self.sounds_to_play.push(Sounds::DropPod);
+        self.set_pod_status(PodStatus::Dropping);
         self.pod_pos_x = self.mothership_pos_x + 25.0 + self.mothership_direction as f32;
         self.pod_pos_y = self.mothership_pos_y + 30.0;
+        self.scraping = false;
+        self.emit(EventKind::PodDropped);
     }
 
     pub fn launch_pod(&mut self) {
@@ -789,13 +3042,18 @@ impl Game {
         {
             return;
         }
-        self.pod_status = PodStatus::Ascending;
-        self.sounds_to_play.push(Sounds::TakeOff);
+        self.set_pod_status(PodStatus::Ascending);
+        self.emit(EventKind::Launched);
     }
 
     pub fn pod_manoeuvre(&mut self) {
+        if self.game_status != GameStatus::Playing {
+            return;
+        }
         if self.pod_status == PodStatus::Dropping || self.pod_status == PodStatus::Ascending {
-            self.pod_pos_x += self.pod_delta as f32 * self.size_multiplier
+            // The pod can't be steered off the edge of the screen
+            self.pod_pos_x = (self.pod_pos_x + self.pod_delta as f32 * self.size_multiplier)
+                .clamp(0.0, self.window_width as f32 - self.pod_size);
         }
     }
 
@@ -810,7 +3068,7 @@ impl Game {
         self.pod_delta = d;
     }
 
-    pub fn get_pod_delta(&mut self) -> i8 {
+    pub fn get_pod_delta(&self) -> i8 {
         self.pod_delta
     }
 }
@@ -0,0 +1,317 @@
+use std::cmp::Reverse;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::daily::DailyResult;
+use crate::difficulty::Difficulty;
+use crate::mode::GameMode;
+use crate::timeattack::LevelTime;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::Preset;
+
+    fn entry(score: u32, preset: Preset) -> Entry {
+        Entry {
+            score,
+            level: 1,
+            mode: GameMode::Classic,
+            difficulty: Difficulty::preset(preset),
+        }
+    }
+
+    #[test]
+    fn test_ranked_by_difficulty() {
+        let mut scores = HighScores::default();
+        assert!(scores.add(entry(100, Preset::Normal)) == Some(0));
+        assert!(scores.add(entry(500, Preset::Easy)) == Some(0));
+        assert!(scores.add(entry(300, Preset::Normal)) == Some(0));
+        assert!(scores.add(entry(200, Preset::Normal)) == Some(1));
+        let normal = scores.top(GameMode::Classic, &Difficulty::preset(Preset::Normal));
+        assert!(normal.iter().map(|e| e.score).collect::<Vec<_>>() == vec![300, 200, 100]);
+        assert!(
+            scores
+                .top(GameMode::Classic, &Difficulty::preset(Preset::Easy))
+                .len()
+                == 1
+        );
+        assert!(scores
+            .top(GameMode::Classic, &Difficulty::preset(Preset::Hard))
+            .is_empty());
+        // Custom settings that happen to match a preset still aren't the preset
+        let mut custom = Difficulty::preset(Preset::Normal);
+        custom.preset = Preset::Custom;
+        assert!(scores.top(GameMode::Classic, &custom).is_empty());
+        // and endless games have their own table
+        let mut endless = entry(1000, Preset::Normal);
+        endless.mode = GameMode::Endless;
+        assert!(scores.add(endless) == Some(0));
+        assert!(scores.top(GameMode::Classic, &Difficulty::default()).len() == 3);
+    }
+
+    #[test]
+    fn test_only_the_best_are_kept() {
+        let mut scores = HighScores::default();
+        assert!(scores.add(entry(0, Preset::Normal)).is_none());
+        for score in 1..=MAX_ENTRIES as u32 {
+            scores.add(entry(score * 10, Preset::Normal));
+        }
+        assert!(scores.add(entry(5, Preset::Normal)).is_none());
+        assert!(scores.add(entry(55, Preset::Normal)) == Some(5));
+        let normal = scores.top(GameMode::Classic, &Difficulty::preset(Preset::Normal));
+        assert!(normal.len() == MAX_ENTRIES);
+        assert!(normal.last().unwrap().score == 20);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = env::temp_dir().join(format!("xtarda2-scores-{}", std::process::id()));
+        let file = path.join("highscores.toml");
+        let mut scores = HighScores::load(&file);
+        assert!(scores.entries.is_empty());
+        scores.add(entry(100, Preset::Hard));
+        scores.save().unwrap();
+        scores.add_run(run(600, Preset::Hard));
+        scores.save().unwrap();
+        let loaded = HighScores::load(&file);
+        fs::remove_dir_all(&path).unwrap();
+        assert!(loaded.entries == scores.entries);
+        assert!(loaded.runs == scores.runs);
+    }
+
+    #[test]
+    fn test_one_daily_result_a_day() {
+        let mut scores = HighScores::default();
+        let result = DailyResult {
+            day: 20_000,
+            score: 300,
+            level: 2,
+        };
+        assert!(scores.add_daily(result.clone()));
+        assert!(!scores.add_daily(DailyResult {
+            score: 900,
+            ..result.clone()
+        }));
+        assert!(scores.daily_result(20_000) == Some(&result));
+        assert!(scores.daily_result(20_001).is_none());
+        scores.update_daily(DailyResult {
+            score: 500,
+            ..result.clone()
+        });
+        assert!(scores.daily_result(20_000).unwrap().score == 500);
+    }
+
+    fn run(frames: u32, preset: Preset) -> Run {
+        Run {
+            frames,
+            difficulty: Difficulty::preset(preset),
+            levels: vec![LevelTime {
+                level: 1,
+                frames,
+                par: 900,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_quickest_runs_first() {
+        let mut scores = HighScores::default();
+        assert!(scores.add_run(run(900, Preset::Normal)) == Some(0));
+        assert!(scores.add_run(run(600, Preset::Normal)) == Some(0));
+        assert!(scores.add_run(run(700, Preset::Normal)) == Some(1));
+        assert!(scores.add_run(run(100, Preset::Easy)) == Some(0));
+        let normal = scores.fastest(&Difficulty::preset(Preset::Normal));
+        assert!(normal.iter().map(|r| r.frames).collect::<Vec<_>>() == vec![600, 700, 900]);
+        for _ in 0..MAX_ENTRIES {
+            scores.add_run(run(500, Preset::Normal));
+        }
+        assert!(scores.add_run(run(550, Preset::Normal)).is_none());
+        assert!(scores.fastest(&Difficulty::preset(Preset::Easy)).len() == 1);
+    }
+}
+
+// Kept for each difficulty
+const MAX_ENTRIES: usize = 10;
+
+/// Where the game keeps what it saves: `$XDG_DATA_HOME/xtarda2`, or
+/// `~/.local/share/xtarda2` if that isn't set
+pub fn data_dir() -> Option<PathBuf> {
+    let data = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+    Some(data.join("xtarda2"))
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Entry {
+    pub score: u32,
+    /// Level the game ended on
+    pub level: u32,
+    #[serde(default)]
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+}
+
+/// A finished time-attack run
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Run {
+    /// The time for the whole run
+    pub frames: u32,
+    pub difficulty: Difficulty,
+    pub levels: Vec<LevelTime>,
+}
+
+/// The best scores for each mode and difficulty, and the quickest
+/// time-attack runs, saved as TOML
+#[derive(Default, Deserialize, Serialize)]
+pub struct HighScores {
+    #[serde(default, rename = "entry")]
+    pub entries: Vec<Entry>,
+    #[serde(default, rename = "run")]
+    pub runs: Vec<Run>,
+    /// One result for each day the daily run was played
+    #[serde(default)]
+    pub daily: Vec<DailyResult>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl HighScores {
+    /// Where high scores go
+    pub fn default_path() -> Option<PathBuf> {
+        Some(data_dir()?.join("highscores.toml"))
+    }
+
+    /// Reads the scores saved at `path`, which is also where they'll be
+    /// saved. A missing file is an empty table.
+    pub fn load(path: &Path) -> HighScores {
+        let mut scores = match fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents).unwrap_or_else(|e| {
+                eprintln!("Ignoring high scores in {}: {}", path.display(), e);
+                HighScores::default()
+            }),
+            Err(_) => HighScores::default(),
+        };
+        scores.path = Some(path.to_path_buf());
+        scores
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let contents = toml::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(path, contents).map_err(|e| e.to_string())
+    }
+
+    /// Adds a score, returning its place among those on the same mode and
+    /// difficulty, or None if it wasn't good enough to keep
+    pub fn add(&mut self, entry: Entry) -> Option<usize> {
+        if entry.score == 0 {
+            return None;
+        }
+        let (mode, difficulty) = (entry.mode, entry.difficulty);
+        insert_ranked(
+            &mut self.entries,
+            entry,
+            |e| e.mode == mode && e.difficulty == difficulty,
+            |e| Reverse(e.score),
+        )
+    }
+
+    /// Adds a finished time-attack run, returning its place among those on
+    /// the same difficulty, or None if it wasn't quick enough to keep
+    pub fn add_run(&mut self, run: Run) -> Option<usize> {
+        let difficulty = run.difficulty;
+        insert_ranked(
+            &mut self.runs,
+            run,
+            |r| r.difficulty == difficulty,
+            |r| r.frames,
+        )
+    }
+
+    /// Best first, only counting games played in this mode on exactly this
+    /// difficulty
+    pub fn top(&self, mode: GameMode, difficulty: &Difficulty) -> Vec<&Entry> {
+        let mut entries: Vec<&Entry> = self
+            .entries
+            .iter()
+            .filter(|e| e.mode == mode && e.difficulty == *difficulty)
+            .collect();
+        entries.sort_by_key(|e| Reverse(e.score));
+        entries
+    }
+
+    /// Records the daily run, unless that day has already been played
+    pub fn add_daily(&mut self, result: DailyResult) -> bool {
+        if self.daily_result(result.day).is_some() {
+            return false;
+        }
+        self.daily.push(result);
+        self.daily.sort_by_key(|r| r.day);
+        true
+    }
+
+    /// Records how the day's run went, in place of what was recorded when
+    /// it started
+    pub fn update_daily(&mut self, result: DailyResult) {
+        match self.daily.iter_mut().find(|r| r.day == result.day) {
+            Some(existing) => *existing = result,
+            None => {
+                self.add_daily(result);
+            }
+        }
+    }
+
+    pub fn daily_result(&self, day: u32) -> Option<&DailyResult> {
+        self.daily.iter().find(|r| r.day == day)
+    }
+
+    /// Quickest first, only counting runs on exactly this difficulty
+    pub fn fastest(&self, difficulty: &Difficulty) -> Vec<&Run> {
+        let mut runs: Vec<&Run> = self
+            .runs
+            .iter()
+            .filter(|r| r.difficulty == *difficulty)
+            .collect();
+        runs.sort_by_key(|r| r.frames);
+        runs
+    }
+}
+
+// Puts `item` in `list` if it's among the best of those in its table, where
+// lower keys are better, and drops whatever in that table no longer makes
+// the cut. Returns where it came in its table.
+fn insert_ranked<T, K: Ord>(
+    list: &mut Vec<T>,
+    item: T,
+    same_table: impl Fn(&T) -> bool,
+    key: impl Fn(&T) -> K,
+) -> Option<usize> {
+    let rank = list
+        .iter()
+        .filter(|other| same_table(other) && key(other) <= key(&item))
+        .count();
+    if rank >= MAX_ENTRIES {
+        return None;
+    }
+    list.push(item);
+    list.sort_by_key(&key);
+    let mut kept = 0;
+    list.retain(|other| {
+        if !same_table(other) {
+            return true;
+        }
+        kept += 1;
+        kept <= MAX_ENTRIES
+    });
+    Some(rank)
+}
@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize, Serializer};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEVEL: &str = r#"
+        name = "Test"
+        mothership_speed = 6
+        men_to_rescue = 3
+        pods = 2
+
+        [landing_pad]
+        x = 0.25
+        width = 200
+
+        [rules]
+        autodock = false
+
+        [[asteroid]]
+        y = 0.2
+        x = 0.5
+        speed = -2.0
+
+        [[asteroid]]
+        y = 0.4
+        x = 0.1
+        speed = 3.0
+        radii = [10, 20, 10]
+        offsets = [0, 0, 0]
+    "#;
+
+    #[test]
+    fn test_level_from_toml() {
+        let level = LevelDefinition::from_toml(LEVEL).unwrap();
+        assert!(level.name == "Test");
+        assert!(level.mothership_speed == 6);
+        assert!(level.men_to_rescue == 3);
+        assert!(level.landing_pad.width == 200.0);
+        assert!(level.asteroids.len() == 2);
+        // sizes default to a middling asteroid
+        assert!(level.asteroids[0].radii == [30.0, 40.0, 30.0]);
+        assert!(level.asteroids[1].offsets == [0.0, 0.0, 0.0]);
+        assert!(!level.rules.autodock);
+        assert!(level.rules.carry_over_pods);
+    }
+
+    #[test]
+    fn test_level_from_toml_errors() {
+        assert!(LevelDefinition::from_toml("men_to_rescue = 1\npods = 1\nfoo = 2").is_err());
+        assert!(LevelDefinition::from_toml("men_to_rescue = 0\npods = 1").is_err());
+        assert!(LevelDefinition::from_toml("men_to_rescue = 1\npods = 0").is_err());
+        let off_screen = "men_to_rescue = 1\npods = 1\n[[asteroid]]\ny = 1.5\nx = 0.5\nspeed = 1.0";
+        assert!(LevelDefinition::from_toml(off_screen).is_err());
+        let stationary = "men_to_rescue = 1\npods = 1\n[[asteroid]]\ny = 0.5\nx = 0.5\nspeed = 0.0";
+        assert!(LevelDefinition::from_toml(stationary).is_err());
+        // Too fast either way, including one that can't be turned round
+        for speed in [0, 31, -31, -128] {
+            let level = format!("men_to_rescue = 1\npods = 1\nmothership_speed = {}", speed);
+            assert!(LevelDefinition::from_toml(&level).is_err());
+        }
+        assert!(
+            LevelDefinition::from_toml("men_to_rescue = 1\npods = 1\nmothership_speed = -30")
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_level_round_trip() {
+        let level = LevelDefinition::from_toml(LEVEL).unwrap();
+        let contents = level.to_toml().unwrap();
+        assert!(contents.contains("x = 0.25\n"));
+        let reloaded = LevelDefinition::from_toml(&contents).unwrap();
+        assert!(reloaded.name == "Test");
+        assert!(reloaded.asteroids.len() == 2);
+        assert!(reloaded.asteroids[1].radii == [10.0, 20.0, 10.0]);
+        assert!(reloaded.asteroids[0].offsets_x == default_offsets_x());
+        assert!(!reloaded.rules.autodock);
+    }
+
+    #[test]
+    fn test_level_number() {
+        assert!(level_number(Path::new("res/levels/level_3.toml")) == Some(3));
+        assert!(level_number(Path::new("res/levels/level_x.toml")).is_none());
+        assert!(level_number(Path::new("res/levels/level_3.ron")).is_none());
+        assert!(level_number(Path::new("res/levels/notes.toml")).is_none());
+    }
+
+    #[test]
+    fn test_example_level() {
+        let example = fs::read_to_string("res/levels/example.toml").unwrap();
+        assert!(LevelDefinition::from_toml(&example).is_ok());
+        // It's only an example, so every level is still made up
+        assert!(LevelDefinition::load_all(Path::new("res/levels")).is_empty());
+    }
+}
+
+// Positions are fractions of the screen so a level looks the same in any
+// window. Sizes and speeds are in pixels at 1920 wide and are scaled with
+// the window like everything else.
+
+// f32s are written as f64s, so 0.3 would be saved as 0.30000001192092896
+// unless it goes through its shortest f32 form first
+fn short<S: Serializer>(value: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(value.to_string().parse().unwrap_or_default())
+}
+
+fn short_array<S: Serializer>(values: &[f32; 3], serializer: S) -> Result<S::Ok, S::Error> {
+    let values: Vec<f64> = values
+        .iter()
+        .map(|v| v.to_string().parse().unwrap_or_default())
+        .collect();
+    values.serialize(serializer)
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AsteroidRow {
+    /// Top of the row, from 0.0 (top of the screen) to 1.0 (bottom)
+    #[serde(serialize_with = "short")]
+    pub y: f32,
+    /// Starting position, from 0.0 (left) to 1.0 (right)
+    #[serde(serialize_with = "short")]
+    pub x: f32,
+    /// Pixels per frame, negative to move left
+    #[serde(serialize_with = "short")]
+    pub speed: f32,
+    /// Radius of each of the asteroid's three blobs
+    #[serde(default = "default_radii", serialize_with = "short_array")]
+    pub radii: [f32; 3],
+    /// How far each blob sits below the top of the row
+    #[serde(default = "default_offsets", serialize_with = "short_array")]
+    pub offsets: [f32; 3],
+    /// How far each blob sits to the right of the row's position
+    #[serde(default = "default_offsets_x", serialize_with = "short_array")]
+    pub offsets_x: [f32; 3],
+}
+
+pub fn default_radii() -> [f32; 3] {
+    [30.0, 40.0, 30.0]
+}
+
+pub fn default_offsets() -> [f32; 3] {
+    [15.0, 5.0, 15.0]
+}
+
+pub fn default_offsets_x() -> [f32; 3] {
+    [0.0, 20.0, 60.0]
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LandingPad {
+    /// Centre of the pad, from 0.0 (left) to 1.0 (right)
+    #[serde(serialize_with = "short")]
+    pub x: f32,
+    #[serde(serialize_with = "short")]
+    pub width: f32,
+}
+
+impl Default for LandingPad {
+    fn default() -> Self {
+        LandingPad {
+            x: 0.5,
+            width: 250.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct Rules {
+    /// Unused pods are kept for this level
+    pub carry_over_pods: bool,
+    /// Docking without help earns an extra pod
+    pub docking_bonus: bool,
+    /// A pod that misses the mothership is steered in. Without it, missing
+    /// the mothership loses the pod.
+    pub autodock: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            carry_over_pods: true,
+            docking_bonus: true,
+            autodock: true,
+        }
+    }
+}
+
+/// A hand-made level, read from `levels/level_<n>.toml`
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LevelDefinition {
+    #[serde(default)]
+    pub name: String,
+    /// Pixels per frame, negative to start off moving left
+    #[serde(default = "default_mothership_speed")]
+    pub mothership_speed: i8,
+    pub men_to_rescue: u32,
+    /// Pods given at the start of the level
+    pub pods: u32,
+    /// Left edge of the moonbase, from 0.0 (left) to 1.0 (right)
+    #[serde(default = "default_moonbase", serialize_with = "short")]
+    pub moonbase: f32,
+    #[serde(default)]
+    pub landing_pad: LandingPad,
+    #[serde(default)]
+    pub rules: Rules,
+    #[serde(default, rename = "asteroid")]
+    pub asteroids: Vec<AsteroidRow>,
+}
+
+/// The fastest the mothership can go either way, in pixels per frame
+pub const MAX_MOTHERSHIP_SPEED: i8 = 30;
+
+fn default_mothership_speed() -> i8 {
+    10
+}
+
+fn default_moonbase() -> f32 {
+    0.75
+}
+
+impl Default for LevelDefinition {
+    fn default() -> Self {
+        LevelDefinition {
+            name: String::new(),
+            mothership_speed: default_mothership_speed(),
+            men_to_rescue: 2,
+            pods: 2,
+            moonbase: default_moonbase(),
+            landing_pad: LandingPad::default(),
+            rules: Rules::default(),
+            asteroids: Vec::new(),
+        }
+    }
+}
+
+// "level_12.toml" is level 12
+fn level_number(path: &Path) -> Option<u32> {
+    if path.extension()? != "toml" {
+        return None;
+    }
+    path.file_stem()?
+        .to_str()?
+        .strip_prefix("level_")?
+        .parse()
+        .ok()
+}
+
+impl LevelDefinition {
+    pub fn from_toml(contents: &str) -> Result<LevelDefinition, String> {
+        let level: LevelDefinition = toml::from_str(contents).map_err(|e| e.to_string())?;
+        if level.men_to_rescue == 0 {
+            return Err("men_to_rescue must be at least 1".to_string());
+        }
+        if level.pods == 0 {
+            return Err("pods must be at least 1".to_string());
+        }
+        if !(1..=MAX_MOTHERSHIP_SPEED.unsigned_abs())
+            .contains(&level.mothership_speed.unsigned_abs())
+        {
+            return Err(format!(
+                "mothership_speed must be between 1 and {} either way",
+                MAX_MOTHERSHIP_SPEED
+            ));
+        }
+        if !(0.0..=1.0).contains(&level.landing_pad.x) || level.landing_pad.width <= 0.0 {
+            return Err("landing pad must be on the screen".to_string());
+        }
+        if !(0.0..=1.0).contains(&level.moonbase) {
+            return Err("moonbase must be on the screen".to_string());
+        }
+        for (idx, row) in level.asteroids.iter().enumerate() {
+            if !(0.0..=1.0).contains(&row.y) || !(0.0..=1.0).contains(&row.x) {
+                return Err(format!("asteroid {} is off the screen", idx + 1));
+            }
+            if row.speed == 0.0 {
+                return Err(format!("asteroid {} doesn't move", idx + 1));
+            }
+            if row.radii.iter().any(|r| *r <= 0.0) {
+                return Err(format!(
+                    "asteroid {} has a radius that isn't positive",
+                    idx + 1
+                ));
+            }
+        }
+        Ok(level)
+    }
+
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string(self).map_err(|e| e.to_string())
+    }
+
+    /// Loads every `level_<n>.toml` in the directory, by level number
+    pub fn load_all(dir: &Path) -> HashMap<u32, LevelDefinition> {
+        let mut levels = HashMap::new();
+        let Ok(entries) = fs::read_dir(dir) else {
+            return levels;
+        };
+        for path in entries.filter_map(|e| e.ok().map(|e| e.path())) {
+            let Some(number) = level_number(&path) else {
+                continue;
+            };
+            match fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|contents| LevelDefinition::from_toml(&contents))
+            {
+                Ok(level) => {
+                    levels.insert(number, level);
+                }
+                Err(e) => eprintln!("Ignoring level file {}: {}", path.display(), e),
+            }
+        }
+        levels
+    }
+}
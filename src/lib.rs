@@ -0,0 +1,29 @@
+//! Everything but the window and the main loop, so that tests, fuzzers and
+//! other programs can play the game too
+
+pub mod achievements;
+pub mod attract;
+pub mod audio;
+pub mod beeper;
+pub mod bot;
+pub mod daily;
+pub mod difficulty;
+pub mod editor;
+pub mod env;
+pub mod events;
+pub mod fuzz;
+pub mod game;
+pub mod highscores;
+pub mod level;
+pub mod mode;
+pub mod music;
+pub mod options;
+pub mod profile;
+pub mod remote;
+pub mod scenario;
+pub mod solver;
+pub mod speccy;
+pub mod state;
+pub mod stats;
+pub mod theme;
+pub mod timeattack;
@@ -1,13 +1,16 @@
 use std::path::Path;
 use std::process::exit;
+use std::sync::mpsc;
 
-use game::GameStatus;
-use sfml::audio::{Music, SoundStatus};
 use sfml::graphics::{Color, RenderTarget, RenderWindow};
 use sfml::system::Vector2i;
-use sfml::window::{joystick, ContextSettings, Event, Key, Style, VideoMode};
+use sfml::window::{joystick, mouse, ContextSettings, Event, Key, Style, VideoMode};
 
-mod game;
+use xtarda2::game::GameStatus;
+use xtarda2::{
+    achievements, audio, bot, env, events, game, highscores, mode, profile, remote, scenario,
+    solver, speccy, stats,
+};
 
 fn main() {
     let window_width = (VideoMode::desktop_mode().width as f32 * 0.8) as u32;
@@ -26,6 +29,150 @@ fn main() {
         resource_path = "../".to_string() + &resource_path;
     }
 
+    // --solvability FIRST..LAST [LEVELS] reports how often generated levels
+    // can be got through, without opening a window. Add --endless to check
+    // endless levels instead.
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(idx) = args.iter().position(|arg| arg == "--solvability") {
+        let seeds = args
+            .get(idx + 1)
+            .and_then(|range| range.split_once(".."))
+            .and_then(|(first, last)| Some(first.parse().ok()?..last.parse().ok()?));
+        let Some(seeds) = seeds else {
+            eprintln!("Usage: xtarda2 --solvability FIRST..LAST [LEVELS] [--endless]");
+            exit(1);
+        };
+        let levels = args.get(idx + 2).and_then(|l| l.parse().ok()).unwrap_or(5);
+        let mut game = game::Game::new(1920, 1280, resource_path);
+        if args.iter().any(|arg| arg == "--endless") {
+            game.set_mode(mode::GameMode::Endless);
+        }
+        println!("{}", solver::survey(&mut game, seeds, levels, 20.0));
+        exit(0);
+    }
+
+    // --env plays headless games for another process, taking JSON requests
+    // on stdin and answering on stdout, a line each. Add --endless for
+    // endless levels.
+    if args.iter().any(|arg| arg == "--env") {
+        let mut game = game::Game::new(1920, 1280, resource_path);
+        game.new_level(1);
+        let mode = if args.iter().any(|arg| arg == "--endless") {
+            mode::GameMode::Endless
+        } else {
+            mode::GameMode::Classic
+        };
+        let mut env = env::Env::new(game, mode);
+        if let Err(e) = env.serve(std::io::stdin().lock(), std::io::stdout().lock()) {
+            eprintln!("Couldn't talk to the agent: {}", e);
+            exit(1);
+        }
+        exit(0);
+    }
+
+    // --scenario FILE... plays scripted scenarios without a window and says
+    // how each went
+    if let Some(idx) = args.iter().position(|arg| arg == "--scenario") {
+        let mut failed = false;
+        for path in args
+            .iter()
+            .skip(idx + 1)
+            .take_while(|arg| !arg.starts_with("--"))
+        {
+            let mut game = game::Game::new(1920, 1280, resource_path.clone());
+            match scenario::Scenario::load(std::path::Path::new(path))
+                .and_then(|scenario| scenario.run(&mut game))
+            {
+                Ok(()) => println!("{}: ok", path),
+                Err(e) => {
+                    println!("{}: {}", path, e);
+                    failed = true;
+                }
+            }
+        }
+        exit(if failed { 1 } else { 0 });
+    }
+
+    // --yardstick FIRST..LAST [MINUTES] has bots of every skill play each
+    // difficulty preset, to see how hard it is
+    if let Some(idx) = args.iter().position(|arg| arg == "--yardstick") {
+        let seeds = args
+            .get(idx + 1)
+            .and_then(|range| range.split_once(".."))
+            .and_then(|(first, last)| Some(first.parse().ok()?..last.parse().ok()?));
+        let Some(seeds) = seeds else {
+            eprintln!("Usage: xtarda2 --yardstick FIRST..LAST [MINUTES]");
+            exit(1);
+        };
+        let minutes = args
+            .get(idx + 2)
+            .and_then(|m| m.parse().ok())
+            .unwrap_or(3.0);
+        let mut game = game::Game::new(1920, 1280, resource_path);
+        println!("{}", bot::yardstick(&mut game, seeds, minutes));
+        exit(0);
+    }
+
+    // --export-stats FILE writes every player's statistics to FILE, as CSV
+    // or JSON depending on whether it ends in .csv or .json
+    if let Some(idx) = args.iter().position(|arg| arg == "--export-stats") {
+        let Some(path) = args.get(idx + 1) else {
+            eprintln!("Usage: xtarda2 --export-stats FILE");
+            exit(1);
+        };
+        let profiles = profile::Profiles::default_path()
+            .map(|p| profile::Profiles::load(&p))
+            .unwrap_or_default();
+        if let Err(e) = stats::export(profiles.all(), Path::new(path)) {
+            eprintln!("Couldn't export statistics: {}", e);
+            exit(1);
+        }
+        exit(0);
+    }
+
+    // --autopilot [novice|pilot|ace] lets a bot fly the pods
+    let mut autopilot = args.iter().position(|arg| arg == "--autopilot").map(|idx| {
+        let skill = args
+            .get(idx + 1)
+            .and_then(|name| bot::Skill::from_name(name));
+        bot::Bot::new(skill.unwrap_or(bot::Skill::Ace))
+    });
+
+    // --remote PATH lets scripts drive the game through a Unix domain socket
+    let mut remote = match args.iter().position(|arg| arg == "--remote") {
+        Some(idx) => {
+            let Some(path) = args.get(idx + 1) else {
+                eprintln!("Usage: xtarda2 --remote PATH");
+                exit(1);
+            };
+            match remote::Remote::bind(std::path::Path::new(path)) {
+                Ok(remote) => Some(remote),
+                Err(e) => {
+                    eprintln!("Couldn't listen on {}: {}", path, e);
+                    exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+
+    // --log-events PATH writes everything that happens in each game to PATH,
+    // as a line of JSON each
+    let mut bus = events::EventBus::default();
+    if let Some(idx) = args.iter().position(|arg| arg == "--log-events") {
+        let Some(path) = args.get(idx + 1) else {
+            eprintln!("Usage: xtarda2 --log-events PATH");
+            exit(1);
+        };
+        match std::fs::File::create(path) {
+            Ok(file) => bus.subscribe(Box::new(events::EventLog::new(file))),
+            Err(e) => {
+                eprintln!("Couldn't write to {}: {}", path, e);
+                exit(1);
+            }
+        }
+    }
+
     let mode = VideoMode::new(window_width, window_height, 32);
     let settings = ContextSettings::default();
 
@@ -41,38 +188,44 @@ fn main() {
     window.set_position(Vector2i::new(50, 50));
     window.set_mouse_cursor_visible(false);
     window.set_key_repeat_enabled(false);
-    let explosion =
-        sfml::audio::SoundBuffer::from_file(&(resource_path.clone() + "/explosion.wav")).unwrap();
-    let mut explosion_sound = sfml::audio::Sound::with_buffer(&explosion);
-    let scrape =
-        sfml::audio::SoundBuffer::from_file(&(resource_path.clone() + "/scrape.wav")).unwrap();
-    let mut scrape_sound = sfml::audio::Sound::with_buffer(&scrape);
-    let landed =
-        sfml::audio::SoundBuffer::from_file(&(resource_path.clone() + "/success.wav")).unwrap();
-    let mut landed_sound = sfml::audio::Sound::with_buffer(&landed);
-    let docked =
-        sfml::audio::SoundBuffer::from_file(&(resource_path.clone() + "/docked.wav")).unwrap();
-    let mut docked_sound = sfml::audio::Sound::with_buffer(&docked);
-    let seatbelt =
-        sfml::audio::SoundBuffer::from_file(&(resource_path.clone() + "/seatbelt.wav")).unwrap();
-    let mut seatbelt_sound = sfml::audio::Sound::with_buffer(&seatbelt);
-    let take_off =
-        sfml::audio::SoundBuffer::from_file(&(resource_path.clone() + "/take_off.wav")).unwrap();
-    let mut take_off_sound = sfml::audio::Sound::with_buffer(&take_off);
-    let bonus =
-        sfml::audio::SoundBuffer::from_file(&(resource_path.clone() + "/bonus.wav")).unwrap();
-    let mut bonus_sound = sfml::audio::Sound::with_buffer(&bonus);
-    let drop_pod =
-        sfml::audio::SoundBuffer::from_file(&(resource_path.clone() + "/drop_pod.wav")).unwrap();
-    let mut drop_pod_sound = sfml::audio::Sound::with_buffer(&drop_pod);
-    let music_file = resource_path.clone() + "/background.wav";
-    let mut music = Music::from_file(&music_file).unwrap();
-    music.set_looping(true);
-    music.play();
-    let mut music_muted = false;
+    let use_beeper = std::env::args().any(|arg| arg == "--beeper");
+    let silent = std::env::args().any(|arg| arg == "--silent");
+    let sound_bank = audio::SoundBank::load(&resource_path, use_beeper, silent);
+    let mut audio = audio::AudioManager::new(&sound_bank);
 
     let mut game = game::Game::new(window_width, window_height, resource_path);
+    let mut speccy_display = speccy::SpeccyDisplay::new(window_width, window_height).ok();
     game.new_level(1);
+    game.set_autopiloted(autopilot.is_some());
+    if let Some(path) = highscores::HighScores::default_path() {
+        game.load_high_scores(&path);
+    }
+    if let Some(path) = profile::Profiles::default_path() {
+        game.load_profiles(&path);
+    }
+    // --profile NAME plays as that player, who is remembered for next time
+    if let Some(name) = args
+        .iter()
+        .position(|arg| arg == "--profile")
+        .and_then(|idx| args.get(idx + 1))
+    {
+        game.select_profile(name);
+    }
+    // The player's statistics and achievements are kept from the same
+    // events, with new achievements sent back for the game to announce
+    bus.subscribe(Box::new(stats::StatsRecorder::new(game.profiles())));
+    let (unlocked_sender, unlocked) = mpsc::channel();
+    bus.subscribe(Box::new(achievements::AchievementsRecorder::new(
+        game.profiles(),
+        unlocked_sender,
+    )));
+    // Sound effects are cued by the same events, apart from scrapes, which
+    // the game queues up itself as they change every frame
+    let (sound_sender, cued_sounds) = mpsc::channel();
+    bus.subscribe(Box::new(audio::SoundCues::new(
+        sound_sender,
+        window_width as f32,
+    )));
     if game.debugging_aids {
         window.set_framerate_limit(10);
     } else {
@@ -81,12 +234,32 @@ fn main() {
 
     // Main Loop
     while window.is_open() {
-        if game.game_status == GameStatus::Paused {
-            music.pause();
-        } else if music.status() != SoundStatus::PLAYING && !music_muted {
-            music.play();
-        }
+        audio.apply_settings(game.audio_settings());
+        audio.update(
+            game.music_context(),
+            game.music_intensity(),
+            game.game_status() == GameStatus::Paused,
+        );
         while let Some(event) = window.poll_event() {
+            if game.game_status() == GameStatus::Editor {
+                handle_editor_event(&mut game, &window, event);
+                continue;
+            }
+            // Any key goes back to the title screen from attract mode, and
+            // does nothing else
+            if game.in_attract_mode() {
+                match event {
+                    Event::Closed => window.close(),
+                    Event::KeyReleased { .. } | Event::JoystickButtonReleased { .. } => {
+                        game.stop_attract();
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            if let Event::KeyPressed { .. } | Event::JoystickButtonPressed { .. } = event {
+                game.stop_attract();
+            }
             if joystick::is_connected(0) {
                 let x = joystick::axis_position(0, joystick::Axis::Z);
                 game.pod_set_delta(convert_joystick_to_delta(x));
@@ -96,16 +269,15 @@ fn main() {
                 }
                 if joystick::is_button_pressed(0, 1) {
                     // Button A
-                    if game.game_status == game::GameStatus::GameOver {
+                    if game.game_status() == GameStatus::GameOver {
                         game.restart();
-                    }
-                    if game.game_status != game::GameStatus::GameOver {
-                        game.game_status = game::GameStatus::Playing;
+                    } else {
+                        game.start();
                     }
                 }
                 if joystick::is_button_pressed(0, 2) {
                     // Button B
-                    if game.game_status == game::GameStatus::GameOver {
+                    if game.game_status() == GameStatus::GameOver {
                         exit(0);
                     }
                 }
@@ -113,46 +285,61 @@ fn main() {
             match event {
                 Event::Closed => window.close(),
                 Event::LostFocus => {
-                    game.game_status = GameStatus::Paused;
-                },
+                    game.pause();
+                }
                 Event::KeyReleased { code, .. } => match code {
-                    Key::Escape => {
-                        window.close();
-                    }
+                    Key::Escape => match game.game_status() {
+                        GameStatus::LevelSelect => game.close_level_select(),
+                        GameStatus::Stats => game.close_stats(),
+                        GameStatus::Achievements => game.close_achievements(),
+                        _ => window.close(),
+                    },
                     Key::P => {
-                        if game.game_status == GameStatus::Playing {
-                            game.game_status = GameStatus::Paused;
-                        }
+                        game.pause();
                     }
-                    Key::M => {
-                        if game.game_status != GameStatus::Paused {
-                            if music.status() == SoundStatus::PLAYING {
-                                music.pause();
-                                music_muted = true;
-                            } else {
-                                music.play();
-                                music_muted = false;
-                            }
-                        }
+                    Key::M if game.game_status() != GameStatus::Paused => {
+                        audio.toggle_music();
                     }
                     Key::Q => {
                         window.close();
                     }
-                    Key::Y => {
-                        if game.game_status == game::GameStatus::GameOver {
-                            game.restart();
-                        }
+                    Key::Y if game.game_status() == GameStatus::GameOver => {
+                        game.restart();
                     }
-                    Key::N => {
-                        if game.game_status == game::GameStatus::GameOver {
-                            exit(0);
-                        }
+                    Key::N if game.game_status() == GameStatus::GameOver => {
+                        exit(0);
+                    }
+                    Key::O => {
+                        game.show_options();
+                    }
+                    Key::S => {
+                        game.show_stats();
+                    }
+                    Key::A => {
+                        game.show_achievements();
+                    }
+                    Key::E => {
+                        game.open_editor();
+                    }
+                    Key::F => {
+                        game.toggle_freeze();
+                    }
+                    Key::R => {
+                        game.restart_level();
                     }
                     Key::Down => {
-                        game.drop_pod();
+                        if game.game_status() == GameStatus::Options {
+                            game.options_select(1);
+                        } else {
+                            game.drop_pod();
+                        }
                     }
                     Key::Up => {
-                        game.launch_pod();
+                        if game.game_status() == GameStatus::Options {
+                            game.options_select(-1);
+                        } else {
+                            game.launch_pod();
+                        }
                     }
                     Key::Space => {
                         game.drop_pod();
@@ -164,63 +351,119 @@ fn main() {
                         game.pod_set_delta(0);
                     }
                     Key::Enter => {
-                        if game.game_status != game::GameStatus::GameOver {
-                            game.game_status = game::GameStatus::Playing;
-                        }
+                        game.start();
                     }
                     _ => {}
                 },
                 Event::KeyPressed { code, .. } => match code {
                     Key::Left => {
-                        game.pod_set_delta(-4);
+                        if game.game_status() == GameStatus::Options {
+                            game.options_change(-1);
+                        } else if game.game_status() == GameStatus::SplashScreen {
+                            game.change_mode(-1);
+                        } else if game.game_status() == GameStatus::LevelSelect {
+                            game.select_level(-1);
+                        } else {
+                            game.pod_set_delta(-4);
+                        }
                     }
                     Key::Right => {
-                        game.pod_set_delta(4);
+                        if game.game_status() == GameStatus::Options {
+                            game.options_change(1);
+                        } else if game.game_status() == GameStatus::SplashScreen {
+                            game.change_mode(1);
+                        } else if game.game_status() == GameStatus::LevelSelect {
+                            game.select_level(1);
+                        } else {
+                            game.pod_set_delta(4);
+                        }
                     }
                     _ => {}
                 },
                 _ => {} // ignore other events
             }
         }
+        if let Some(remote) = remote.as_mut() {
+            remote.poll(&mut game);
+        }
+        if let Some(bot) = autopilot.as_mut() {
+            bot.fly(&mut game);
+        }
         if game.get_pod_delta() != 0 {
             game.pod_manoeuvre();
         }
         window.clear(Color::BLACK);
         game.next_frame();
-        game.draw_screen(&mut window);
+        match speccy_display.as_mut() {
+            Some(display) if game.speccy_mode() => display.draw(&mut game, &mut window),
+            _ => game.draw_screen(&mut *window),
+        }
         for sound in &game.sounds_to_play {
-            match sound {
-                game::Sounds::Docked => {
-                    docked_sound.play();
-                }
-                game::Sounds::Explosion => {
-                    explosion_sound.play();
-                }
-                game::Sounds::Landed => {
-                    landed_sound.play();
-                }
-                game::Sounds::Seatbelt => {
-                    seatbelt_sound.play();
-                }
-                game::Sounds::TakeOff => {
-                    take_off_sound.play();
-                }
-                game::Sounds::DropPod => {
-                    drop_pod_sound.play();
-                }
-                game::Sounds::Scrape => {
-                    if scrape_sound.status() != SoundStatus::PLAYING {
-                        scrape_sound.play();
-                    }
-                }
-                game::Sounds::Bonus => {
-                    bonus_sound.play();
-                }
-            }
+            audio.play(sound);
         }
         game.sounds_to_play.clear();
+        bus.publish(&game.events);
+        game.events.clear();
+        for sound in cued_sounds.try_iter() {
+            audio.play(&sound);
+        }
+        for achievement in unlocked.try_iter() {
+            game.announce(achievement);
+        }
+        for change in game.status_changes.drain(..) {
+            // The mouse is only needed in the editor
+            window.set_mouse_cursor_visible(change.to == GameStatus::Editor);
+        }
         window.display();
     }
+    // Closing the window mid-game still keeps what was done in it
+    game.quit();
+    bus.publish(&game.events);
+
+    fn handle_editor_event(game: &mut game::Game, window: &RenderWindow, event: Event) {
+        let to_view = |x: i32, y: i32| window.map_pixel_to_coords_current_view(Vector2i::new(x, y));
+        match event {
+            Event::Closed => exit(0),
+            Event::KeyReleased { code, .. } => match code {
+                Key::Escape => game.close_editor(),
+                Key::Enter => game.editor_play(),
+                Key::S => {
+                    game.editor_save();
+                }
+                Key::PageUp => game.editor_change_level(1),
+                Key::PageDown => game.editor_change_level(-1),
+                _ => {}
+            },
+            _ => {}
+        }
+        game.edit(|editor| match event {
+            Event::MouseButtonPressed { button, x, y } => {
+                let point = to_view(x, y);
+                match button {
+                    mouse::Button::Left => editor.press(point.x, point.y, false),
+                    mouse::Button::Right => editor.press(point.x, point.y, true),
+                    _ => {}
+                }
+            }
+            Event::MouseMoved { x, y } => {
+                let point = to_view(x, y);
+                editor.drag_to(point.x, point.y);
+            }
+            Event::MouseButtonReleased { .. } => editor.release(),
+            Event::MouseWheelScrolled { delta, x, y, .. } => {
+                let point = to_view(x, y);
+                editor.scroll(point.x, point.y, delta);
+            }
+            Event::KeyReleased { code, .. } => match code {
+                Key::Delete | Key::Backspace => editor.delete_selected(),
+                Key::Up => editor.change_mothership_speed(1),
+                Key::Down => editor.change_mothership_speed(-1),
+                Key::R => editor.reverse_mothership(),
+                _ => {}
+            },
+            _ => {}
+        });
+    }
 
     fn convert_joystick_to_delta(x: f32) -> i8 {
         // Joystick values range from -100 to +100
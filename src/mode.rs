@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+
+use crate::difficulty::Difficulty;
+use crate::options;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classic_is_the_original_curve() {
+        let plan = plan(GameMode::Classic, 1);
+        assert!(plan.asteroids == 18);
+        assert!(plan.max_speed == 3.5);
+        assert!(plan.men_to_rescue == 2);
+        assert!(plan.hazard(0.0) == Hazard::Asteroid);
+        assert!(self::plan(GameMode::Classic, 6).asteroids == 28);
+        assert!(self::plan(GameMode::Classic, 6).men_to_rescue == 5);
+    }
+
+    #[test]
+    fn test_any_level_number_is_safe() {
+        for mode in GameMode::ALL {
+            for level in [1, 119, 120, 128, 255, 256, 100_000, u32::MAX] {
+                let plan = plan(mode, level);
+                assert!(plan.asteroids <= MAX_ASTEROIDS);
+                assert!(plan.max_speed <= MAX_SPEED);
+                assert!(plan.men_to_rescue == 5 || level == 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_endless_curve() {
+        let mut last = plan(GameMode::Endless, 1);
+        assert!(last.asteroids == 18);
+        for level in 2..200 {
+            let plan = plan(GameMode::Endless, level);
+            assert!(plan.asteroids >= last.asteroids);
+            assert!(plan.max_speed >= last.max_speed);
+            last = plan;
+        }
+        assert!(last.asteroids == MAX_ASTEROIDS);
+        // Hazards turn up later on
+        assert!(plan(GameMode::Endless, 2).hazard(0.0) == Hazard::Asteroid);
+        let late = plan(GameMode::Endless, 50);
+        assert!(late.hazard(0.0) == Hazard::Streaker);
+        assert!(late.hazard(0.2) == Hazard::Giant);
+        assert!(late.hazard(0.3) == Hazard::Pair);
+        assert!(late.hazard(0.99) == Hazard::Asteroid);
+    }
+}
+
+/// Ways to play, chosen on the title screen
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum GameMode {
+    /// The original game, with any hand-made levels
+    #[default]
+    Classic,
+    /// Made-up levels that keep getting harder for as long as you last
+    Endless,
+    /// A short run of levels against the clock
+    TimeAttack,
+    /// The same levels for everyone each day, with one scored attempt
+    Daily,
+    /// Any level reached so far, with unlimited pods and no scores kept
+    Practice,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 5] = [
+        GameMode::Classic,
+        GameMode::Endless,
+        GameMode::TimeAttack,
+        GameMode::Daily,
+        GameMode::Practice,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::Endless => "Endless",
+            GameMode::TimeAttack => "Time Attack",
+            GameMode::Daily => "Daily Run",
+            GameMode::Practice => "Practice",
+        }
+    }
+
+    /// Whether hand-made levels are played. Modes where everyone should
+    /// get the same levels make all of theirs up.
+    pub fn uses_level_files(&self) -> bool {
+        matches!(
+            self,
+            GameMode::Classic | GameMode::TimeAttack | GameMode::Practice
+        )
+    }
+
+    /// Whether reaching a level in this mode unlocks it for practice
+    pub fn unlocks_levels(&self) -> bool {
+        matches!(self, GameMode::Classic | GameMode::TimeAttack)
+    }
+
+    /// The difficulty a mode is always played on, whatever the options say
+    pub fn fixed_difficulty(&self) -> Option<Difficulty> {
+        match self {
+            GameMode::Daily => Some(Difficulty::default()),
+            _ => None,
+        }
+    }
+
+    pub fn cycle(&self, delta: i32) -> GameMode {
+        let idx = GameMode::ALL.iter().position(|m| m == self).unwrap_or(0);
+        GameMode::ALL[options::cycle(idx, delta, GameMode::ALL.len())]
+    }
+}
+
+/// Things that can be in a row of a made-up level
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Hazard {
+    Asteroid,
+    /// A small asteroid that moves much faster than the rest
+    Streaker,
+    /// A big, slow asteroid
+    Giant,
+    /// Two asteroids flying side by side
+    Pair,
+}
+
+// Neither curve goes past these, so any level can be laid out
+pub const MAX_ASTEROIDS: u32 = 44;
+pub const MAX_SPEED: f32 = 9.5;
+const MAX_MEN: u32 = 5;
+
+/// How a made-up level is put together, before the difficulty is applied
+pub struct LevelPlan {
+    pub asteroids: u32,
+    /// Asteroids move at up to this many pixels per frame either way
+    pub max_speed: f32,
+    pub men_to_rescue: u32,
+    /// The chance of a row being each kind of hazard. Rows that aren't are
+    /// ordinary asteroids.
+    pub hazards: [(Hazard, f32); 3],
+}
+
+impl LevelPlan {
+    /// Picks the hazard for a row from a roll between 0.0 and 1.0
+    pub fn hazard(&self, roll: f32) -> Hazard {
+        let mut total = 0.0;
+        for (hazard, chance) in self.hazards {
+            total += chance;
+            if roll < total {
+                return hazard;
+            }
+        }
+        Hazard::Asteroid
+    }
+}
+
+// Moves from `start` at level 1 towards `limit`, closing the gap by the same
+// fraction every level
+fn approach(start: f32, limit: f32, rate: f32, level: u32) -> f32 {
+    limit - (limit - start) * rate.powf(level.saturating_sub(1) as f32)
+}
+
+// Nothing before level `from`, then rising steadily to `most` at level `to`
+fn ramp(level: u32, from: u32, to: u32, most: f32) -> f32 {
+    if level < from {
+        return 0.0;
+    }
+    most * ((level - from) as f32 / (to - from) as f32).min(1.0)
+}
+
+pub fn plan(mode: GameMode, level: u32) -> LevelPlan {
+    let men_to_rescue = level.saturating_add(1).min(MAX_MEN);
+    match mode {
+        GameMode::Classic | GameMode::TimeAttack | GameMode::Daily | GameMode::Practice => {
+            LevelPlan {
+                asteroids: level
+                    .saturating_mul(2)
+                    .saturating_add(16)
+                    .min(MAX_ASTEROIDS),
+                max_speed: (3.0 + level as f32 / 2.0).min(MAX_SPEED),
+                men_to_rescue,
+                hazards: [
+                    (Hazard::Streaker, 0.0),
+                    (Hazard::Giant, 0.0),
+                    (Hazard::Pair, 0.0),
+                ],
+            }
+        }
+        GameMode::Endless => LevelPlan {
+            asteroids: approach(18.0, MAX_ASTEROIDS as f32, 0.9, level).round() as u32,
+            max_speed: approach(3.5, MAX_SPEED, 0.92, level),
+            men_to_rescue,
+            hazards: [
+                (Hazard::Streaker, ramp(level, 4, 14, 0.15)),
+                (Hazard::Giant, ramp(level, 8, 20, 0.1)),
+                (Hazard::Pair, ramp(level, 12, 30, 0.15)),
+            ],
+        },
+    }
+}
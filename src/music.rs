@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+use std::fs;
+
+use serde::Deserialize;
+
+use crate::beeper;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAYLISTS: &str = r#"
+        sting = "beeper:sting"
+
+        [[track]]
+        name = "orbit"
+        calm = "background.wav"
+
+        [[track]]
+        name = "belt"
+        calm = "beeper:bass"
+        drive = "beeper:arpeggio"
+        intense = "beeper:drums"
+
+        [playlists]
+        splash = ["orbit"]
+        level = ["belt", "orbit"]
+        level_3 = ["orbit"]
+    "#;
+
+    fn library() -> MusicLibrary {
+        MusicLibrary::from_toml(PLAYLISTS, "res", false).unwrap()
+    }
+
+    #[test]
+    fn test_target_levels() {
+        assert!(target_levels(0.0) == [1.0, 0.0, 0.0]);
+        assert!(target_levels(1.0) == [1.0, 1.0, 1.0]);
+        let middle = target_levels(0.5);
+        assert!(middle[1] == 1.0 && middle[2] == 0.0);
+    }
+
+    #[test]
+    fn test_library_from_toml() {
+        let library = library();
+        assert!(library.tracks.len() == 2);
+        assert!(
+            matches!(&library.tracks[0].stems[0], Some(Stem::File(f)) if f == "res/background.wav")
+        );
+        assert!(library.tracks[0].stems[1].is_none());
+        assert!(matches!(library.tracks[1].stems[2], Some(Stem::Memory(_))));
+        assert!(library.sting.is_some());
+        assert!(MusicLibrary::from_toml("[playlists]\nsplash = [\"nope\"]", "res", false).is_err());
+    }
+
+    #[test]
+    fn test_beeper_replaces_files() {
+        let library = MusicLibrary::from_toml(PLAYLISTS, "res", true).unwrap();
+        assert!(matches!(library.tracks[0].stems[0], Some(Stem::Memory(_))));
+    }
+
+    #[test]
+    fn test_playlist_selection() {
+        let library = library();
+        let mut director = MusicDirector::new();
+        assert!(director.set_context(MusicContext::Splash, &library) == Some(0));
+        // Same context, same track
+        assert!(director
+            .set_context(MusicContext::Splash, &library)
+            .is_none());
+        // Levels without their own playlist use "level", working through it
+        assert!(director.set_context(MusicContext::Level(1), &library) == Some(1));
+        assert!(director.set_context(MusicContext::Level(2), &library) == Some(0));
+        // Level 3 has its own playlist, but it's the same track so it carries on
+        assert!(director
+            .set_context(MusicContext::Level(3), &library)
+            .is_none());
+        assert!(director.set_context(MusicContext::Level(4), &library) == Some(1));
+        // No game over playlist, so the music stops
+        assert!(director
+            .set_context(MusicContext::GameOver, &library)
+            .is_none());
+        assert!(director.current_track().is_none());
+    }
+
+    #[test]
+    fn test_level_complete_sting() {
+        let library = library();
+        let mut director = MusicDirector::new();
+        director.set_context(MusicContext::Level(1), &library);
+        assert!(!director.take_sting());
+        director.set_context(MusicContext::Level(2), &library);
+        assert!(director.take_sting());
+        assert!(!director.take_sting());
+        director.set_context(MusicContext::Splash, &library);
+        director.set_context(MusicContext::Level(1), &library);
+        assert!(!director.take_sting());
+    }
+
+    #[test]
+    fn test_layers_follow_intensity_and_duck() {
+        let mut director = MusicDirector::new();
+        for _ in 0..200 {
+            director.update(1.0);
+        }
+        assert!(director.layer_volume(Layer::Intense) > 0.99);
+        director.duck();
+        assert!(director.layer_volume(Layer::Calm) < 0.5);
+        for _ in 0..200 {
+            director.update(0.0);
+        }
+        assert!(director.layer_volume(Layer::Calm) > 0.99);
+        assert!(director.layer_volume(Layer::Intense) < 0.01);
+    }
+}
+
+/// Stems of a track, in the order they join in as the action builds
+#[derive(Clone, Copy)]
+pub enum Layer {
+    Calm,
+    Drive,
+    Intense,
+}
+
+pub const LAYERS: usize = 3;
+
+/// Which playlist should be playing
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MusicContext {
+    Splash,
+    Level(u32),
+    GameOver,
+}
+
+impl MusicContext {
+    fn playlist_names(&self) -> Vec<String> {
+        match self {
+            MusicContext::Splash => vec!["splash".to_string()],
+            MusicContext::GameOver => vec!["game_over".to_string()],
+            MusicContext::Level(level) => vec![format!("level_{}", level), "level".to_string()],
+        }
+    }
+}
+
+pub enum Stem {
+    File(String),
+    /// Generated WAV data
+    Memory(Vec<u8>),
+}
+
+pub struct Track {
+    pub name: String,
+    pub stems: [Option<Stem>; LAYERS],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TrackFile {
+    name: String,
+    calm: String,
+    drive: Option<String>,
+    intense: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlaylistFile {
+    sting: Option<String>,
+    #[serde(default)]
+    track: Vec<TrackFile>,
+    #[serde(default)]
+    playlists: HashMap<String, Vec<String>>,
+}
+
+/// All the music tracks and which contexts play them, as described by
+/// `music/playlists.toml`
+pub struct MusicLibrary {
+    pub tracks: Vec<Track>,
+    playlists: HashMap<String, Vec<usize>>,
+    pub sting: Option<Vec<i16>>,
+}
+
+// "beeper:<name>" stems are generated, anything else is a file under res
+fn load_stem(name: &str, resource_path: &str, use_beeper: bool) -> Result<Stem, String> {
+    let generated = |name: &str| {
+        beeper::music(name)
+            .map(|samples| Stem::Memory(beeper::wav_bytes(&samples)))
+            .ok_or(format!("no beeper music called '{}'", name))
+    };
+    match name.strip_prefix("beeper:") {
+        Some(name) => generated(name),
+        None if use_beeper => generated("bass"),
+        None => Ok(Stem::File(resource_path.to_string() + "/" + name)),
+    }
+}
+
+impl MusicLibrary {
+    pub fn empty() -> MusicLibrary {
+        MusicLibrary {
+            tracks: Vec::new(),
+            playlists: HashMap::new(),
+            sting: None,
+        }
+    }
+
+    /// Loads `music/playlists.toml`, or if there isn't one plays the
+    /// background music everywhere
+    pub fn load(resource_path: &str, use_beeper: bool) -> MusicLibrary {
+        let file = resource_path.to_string() + "/music/playlists.toml";
+        let contents = fs::read_to_string(&file).unwrap_or_else(|_| {
+            "[[track]]\nname = \"background\"\ncalm = \"background.wav\"\n\
+             [playlists]\nsplash = [\"background\"]\nlevel = [\"background\"]\n\
+             game_over = [\"background\"]\n"
+                .to_string()
+        });
+        MusicLibrary::from_toml(&contents, resource_path, use_beeper).unwrap_or_else(|e| {
+            eprintln!("Ignoring {}: {}", file, e);
+            MusicLibrary::empty()
+        })
+    }
+
+    pub fn from_toml(
+        contents: &str,
+        resource_path: &str,
+        use_beeper: bool,
+    ) -> Result<MusicLibrary, String> {
+        let file: PlaylistFile = toml::from_str(contents).map_err(|e| e.to_string())?;
+        let mut tracks = Vec::new();
+        for track in &file.track {
+            let stem = |name: &Option<String>| {
+                name.as_ref()
+                    .map(|n| load_stem(n, resource_path, use_beeper))
+                    .transpose()
+            };
+            tracks.push(Track {
+                name: track.name.clone(),
+                stems: [
+                    Some(load_stem(&track.calm, resource_path, use_beeper)?),
+                    stem(&track.drive)?,
+                    stem(&track.intense)?,
+                ],
+            });
+        }
+        let mut playlists = HashMap::new();
+        for (playlist, names) in &file.playlists {
+            let mut indices = Vec::new();
+            for name in names {
+                let idx = tracks.iter().position(|t| &t.name == name).ok_or(format!(
+                    "playlist {} has unknown track '{}'",
+                    playlist, name
+                ))?;
+                indices.push(idx);
+            }
+            playlists.insert(playlist.clone(), indices);
+        }
+        let sting = match &file.sting {
+            Some(name) => Some(
+                beeper::music(name.strip_prefix("beeper:").unwrap_or(name))
+                    .ok_or(format!("no beeper music called '{}'", name))?,
+            ),
+            None => None,
+        };
+        Ok(MusicLibrary {
+            tracks,
+            playlists,
+            sting,
+        })
+    }
+
+    fn playlist(&self, context: MusicContext) -> Option<(String, &Vec<usize>)> {
+        context
+            .playlist_names()
+            .into_iter()
+            .find_map(|name| self.playlists.get(&name).map(|p| (name, p)))
+            .filter(|(_, p)| !p.is_empty())
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// How loud each layer should be for a gameplay intensity from 0.0
+/// (docked, nothing happening) to 1.0 (in the thick of the asteroids)
+pub fn target_levels(intensity: f32) -> [f32; LAYERS] {
+    [
+        1.0,
+        smoothstep(0.2, 0.5, intensity),
+        smoothstep(0.6, 0.9, intensity),
+    ]
+}
+
+// Per frame fractions; layers fade in over about half a second and the
+// music recovers from a duck in about a second
+const FADE: f32 = 0.05;
+const DUCK_RECOVERY: f32 = 0.02;
+const DUCK_DEPTH: f32 = 0.7;
+
+/// Decides what music plays and how loud each layer is. It knows nothing
+/// about SFML so the audio manager does the actual playing.
+pub struct MusicDirector {
+    context: Option<MusicContext>,
+    current_track: Option<usize>,
+    // Next track to play from each playlist
+    playlist_positions: HashMap<String, usize>,
+    levels: [f32; LAYERS],
+    duck: f32,
+    sting_due: bool,
+}
+
+impl Default for MusicDirector {
+    fn default() -> Self {
+        MusicDirector::new()
+    }
+}
+
+impl MusicDirector {
+    pub fn new() -> MusicDirector {
+        MusicDirector {
+            context: None,
+            current_track: None,
+            playlist_positions: HashMap::new(),
+            levels: target_levels(0.0),
+            duck: 0.0,
+            sting_due: false,
+        }
+    }
+
+    pub fn current_track(&self) -> Option<usize> {
+        self.current_track
+    }
+
+    /// Moves to a new context, returning the track to switch to if it changes.
+    /// Each visit to a context plays the next track in its playlist.
+    pub fn set_context(&mut self, context: MusicContext, library: &MusicLibrary) -> Option<usize> {
+        if self.context == Some(context) {
+            return None;
+        }
+        if let (Some(MusicContext::Level(old)), MusicContext::Level(new)) = (self.context, context)
+        {
+            self.sting_due = new > old;
+        }
+        self.context = Some(context);
+        let track = library.playlist(context).map(|(name, playlist)| {
+            let position = self.playlist_positions.entry(name).or_insert(0);
+            let track = playlist[*position % playlist.len()];
+            *position += 1;
+            track
+        });
+        let changed = track != self.current_track;
+        self.current_track = track;
+        if changed {
+            track
+        } else {
+            None
+        }
+    }
+
+    /// Returns true once after a level has been completed
+    pub fn take_sting(&mut self) -> bool {
+        std::mem::take(&mut self.sting_due)
+    }
+
+    /// Drops the music briefly, e.g. under an explosion
+    pub fn duck(&mut self) {
+        self.duck = DUCK_DEPTH;
+    }
+
+    /// Call once a frame to move the layers towards the given intensity
+    pub fn update(&mut self, intensity: f32) {
+        let targets = target_levels(intensity);
+        for (level, target) in self.levels.iter_mut().zip(targets) {
+            *level += (target - *level).clamp(-FADE, FADE);
+        }
+        self.duck = (self.duck - DUCK_RECOVERY).max(0.0);
+    }
+
+    /// From 0.0 to 1.0
+    pub fn layer_volume(&self, layer: Layer) -> f32 {
+        self.levels[layer as usize] * (1.0 - self.duck)
+    }
+}
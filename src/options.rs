@@ -0,0 +1,74 @@
+use crate::difficulty::Setting;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cycle() {
+        assert!(cycle(0, 1, 4) == 1);
+        assert!(cycle(3, 1, 4) == 0);
+        assert!(cycle(0, -1, 4) == 3);
+        assert!(cycle(2, -6, 4) == 0);
+        assert!(cycle(0, 1, 0) == 0);
+    }
+}
+
+/// Entries on the options screen, in display order
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum OptionItem {
+    Profile,
+    Theme,
+    Display,
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    Difficulty,
+    Curve(Setting),
+}
+
+impl OptionItem {
+    pub const ALL: [OptionItem; 14] = [
+        OptionItem::Profile,
+        OptionItem::Theme,
+        OptionItem::Display,
+        OptionItem::MasterVolume,
+        OptionItem::MusicVolume,
+        OptionItem::SfxVolume,
+        OptionItem::Difficulty,
+        OptionItem::Curve(Setting::Asteroids),
+        OptionItem::Curve(Setting::Speed),
+        OptionItem::Curve(Setting::Size),
+        OptionItem::Curve(Setting::Mothership),
+        OptionItem::Curve(Setting::Tolerance),
+        OptionItem::Curve(Setting::Pods),
+        OptionItem::Curve(Setting::DockingBonus),
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OptionItem::Profile => "Player",
+            OptionItem::Theme => "Theme",
+            OptionItem::Display => "Display",
+            OptionItem::MasterVolume => "Volume",
+            OptionItem::MusicVolume => "Music",
+            OptionItem::SfxVolume => "Effects",
+            OptionItem::Difficulty => "Difficulty",
+            OptionItem::Curve(Setting::Asteroids) => "Asteroids",
+            OptionItem::Curve(Setting::Speed) => "Asteroid speed",
+            OptionItem::Curve(Setting::Size) => "Asteroid size",
+            OptionItem::Curve(Setting::Mothership) => "Mothership speed",
+            OptionItem::Curve(Setting::Tolerance) => "Near miss tolerance",
+            OptionItem::Curve(Setting::Pods) => "Pods",
+            OptionItem::Curve(Setting::DockingBonus) => "Docking bonus",
+        }
+    }
+}
+
+/// Moves `index` by `delta` places through `len` items, wrapping at either end
+pub fn cycle(index: usize, delta: i32, len: usize) -> usize {
+    if len == 0 {
+        return 0;
+    }
+    (index as i64 + delta as i64).rem_euclid(len as i64) as usize
+}
@@ -0,0 +1,325 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::achievements::Achievements;
+use crate::highscores;
+use crate::options;
+use crate::stats::Stats;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::achievements::Achievement;
+    use crate::events::{Event, EventKind, Subscriber};
+
+    #[test]
+    fn test_progress() {
+        let mut profile = Profile::new("Ann");
+        assert!(profile.unlocked_levels() == 1);
+        assert!(profile.reach(3));
+        assert!(!profile.reach(2));
+        assert!(profile.unlocked_levels() == 3);
+        assert!(profile.stars(1) == Stars::default());
+        profile.add_stars(
+            1,
+            Stars {
+                no_crashes: true,
+                ..Stars::default()
+            },
+        );
+        profile.add_stars(
+            1,
+            Stars {
+                under_par: true,
+                ..Stars::default()
+            },
+        );
+        // The best of each is kept
+        assert!(profile.stars(1).count() == 2);
+        profile.add_stars(
+            3,
+            Stars {
+                no_autodock: true,
+                ..Stars::default()
+            },
+        );
+        assert!(profile.total_stars() == 3);
+        assert!(profile.stars(1).to_string() == "*.*");
+    }
+
+    #[test]
+    fn test_profiles() {
+        let mut profiles = Profiles::default();
+        assert!(profiles.current_name() == "Player 1");
+        profiles.current_mut().reach(4);
+        profiles.cycle(1);
+        assert!(profiles.current_name() == "Player 2");
+        assert!(profiles.current().unlocked_levels() == 1);
+        profiles.select("Zed");
+        profiles.cycle(-1);
+        assert!(profiles.current_name() == "Player 1");
+        assert!(profiles.current().unlocked_levels() == 4);
+        profiles.cycle(1);
+        assert!(profiles.current_name() == "Zed");
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("xtarda2-profiles-{}", std::process::id()));
+        let path = dir.join("profiles.toml");
+        let mut profiles = Profiles::load(&path);
+        profiles.select("Ann");
+        profiles.current_mut().reach(2);
+        profiles.current_mut().stats.autodockings = 3;
+        for kind in [
+            EventKind::GameStarted {
+                level: 9,
+                on_record: true,
+            },
+            EventKind::LevelComplete {
+                level: 9,
+                carry_over_pods: true,
+            },
+        ] {
+            profiles.current_mut().achievements.notify(&Event {
+                tick: 0,
+                x: 0.0,
+                y: 0.0,
+                kind,
+            });
+        }
+        profiles.current_mut().add_stars(
+            2,
+            Stars {
+                no_autodock: true,
+                ..Stars::default()
+            },
+        );
+        profiles.save().unwrap();
+        let loaded = Profiles::load(&path);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(loaded.current_name() == "Ann");
+        assert!(loaded.current().unlocked_levels() == 2);
+        assert!(loaded.current().stars(2).no_autodock);
+        assert!(loaded.current().stats.autodockings == 3);
+        assert!(loaded.current().achievements.has(Achievement::Veteran));
+    }
+}
+
+// Offered on the options screen even before anyone has played as them
+const DEFAULT_NAMES: [&str; 4] = ["Player 1", "Player 2", "Player 3", "Player 4"];
+
+/// What a level was finished with, the best ever for each
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Stars {
+    /// No pods lost
+    pub no_crashes: bool,
+    /// Every pod docked without autodock
+    pub no_autodock: bool,
+    /// Finished within its time-attack par
+    pub under_par: bool,
+}
+
+impl Stars {
+    pub fn count(&self) -> u32 {
+        self.no_crashes as u32 + self.no_autodock as u32 + self.under_par as u32
+    }
+}
+
+impl std::fmt::Display for Stars {
+    // "*.*" for a level finished without crashes, under par, but with autodock
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for star in [self.no_crashes, self.no_autodock, self.under_par] {
+            write!(f, "{}", if star { "*" } else { "." })?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct LevelRecord {
+    level: u32,
+    #[serde(flatten)]
+    stars: Stars,
+}
+
+/// One player's progress through the levels
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Profile {
+    pub name: String,
+    /// The furthest level reached, which unlocks it
+    #[serde(default)]
+    pub highest_level: u32,
+    #[serde(default, rename = "level")]
+    levels: Vec<LevelRecord>,
+    #[serde(default)]
+    pub stats: Stats,
+    #[serde(default)]
+    pub achievements: Achievements,
+}
+
+impl Profile {
+    pub fn new(name: &str) -> Profile {
+        Profile {
+            name: name.to_string(),
+            highest_level: 0,
+            levels: Vec::new(),
+            stats: Stats::default(),
+            achievements: Achievements::default(),
+        }
+    }
+
+    /// Notes that a level has been reached, returning whether it's further
+    /// than ever before
+    pub fn reach(&mut self, level: u32) -> bool {
+        if level <= self.highest_level {
+            return false;
+        }
+        self.highest_level = level;
+        true
+    }
+
+    /// Levels 1 to this can be chosen on the level select screen
+    pub fn unlocked_levels(&self) -> u32 {
+        self.highest_level.max(1)
+    }
+
+    pub fn stars(&self, level: u32) -> Stars {
+        self.levels
+            .iter()
+            .find(|r| r.level == level)
+            .map(|r| r.stars)
+            .unwrap_or_default()
+    }
+
+    /// Stars earned across every level
+    pub fn total_stars(&self) -> u32 {
+        self.levels.iter().map(|r| r.stars.count()).sum()
+    }
+
+    /// Adds to the stars for a level, keeping any it already had
+    pub fn add_stars(&mut self, level: u32, stars: Stars) {
+        if !self.levels.iter().any(|r| r.level == level) {
+            self.levels.push(LevelRecord {
+                level,
+                stars: Stars::default(),
+            });
+            self.levels.sort_by_key(|r| r.level);
+        }
+        if let Some(record) = self.levels.iter_mut().find(|r| r.level == level) {
+            record.stars.no_crashes |= stars.no_crashes;
+            record.stars.no_autodock |= stars.no_autodock;
+            record.stars.under_par |= stars.under_par;
+        }
+    }
+}
+
+/// Everyone who has played on this computer, and who is playing now, saved
+/// as TOML
+#[derive(Default, Deserialize, Serialize)]
+pub struct Profiles {
+    #[serde(default)]
+    current: String,
+    #[serde(default, rename = "profile")]
+    profiles: Vec<Profile>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl Profiles {
+    /// Where profiles go, next to the high scores
+    pub fn default_path() -> Option<PathBuf> {
+        Some(highscores::data_dir()?.join("profiles.toml"))
+    }
+
+    /// Reads the profiles saved at `path`, which is also where they'll be
+    /// saved. A missing file means nobody has played yet.
+    pub fn load(path: &Path) -> Profiles {
+        let mut profiles = match fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents).unwrap_or_else(|e| {
+                eprintln!("Ignoring profiles in {}: {}", path.display(), e);
+                Profiles::default()
+            }),
+            Err(_) => Profiles::default(),
+        };
+        profiles.path = Some(path.to_path_buf());
+        profiles
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let contents = toml::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(path, contents).map_err(|e| e.to_string())
+    }
+
+    pub fn current_name(&self) -> &str {
+        if self.current.is_empty() {
+            DEFAULT_NAMES[0]
+        } else {
+            &self.current
+        }
+    }
+
+    /// Everyone who has played
+    pub fn all(&self) -> &[Profile] {
+        &self.profiles
+    }
+
+    /// The current player, who hasn't got anywhere if they've never played
+    pub fn current(&self) -> &Profile {
+        // Stands in for anyone new, so has no name of its own
+        static NEW_PLAYER: LazyLock<Profile> = LazyLock::new(|| Profile::new(""));
+        let name = self.current_name();
+        self.profiles
+            .iter()
+            .find(|p| p.name == name)
+            .unwrap_or(&NEW_PLAYER)
+    }
+
+    /// The current player, added to the profiles if they're new
+    pub fn current_mut(&mut self) -> &mut Profile {
+        let name = self.current_name().to_string();
+        let idx = match self.profiles.iter().position(|p| p.name == name) {
+            Some(idx) => idx,
+            None => {
+                self.profiles.push(Profile::new(&name));
+                self.profiles.len() - 1
+            }
+        };
+        &mut self.profiles[idx]
+    }
+
+    /// Plays as `name`, who is remembered from now on
+    pub fn select(&mut self, name: &str) {
+        self.current = name.to_string();
+        self.current_mut();
+    }
+
+    /// Moves to the next or previous player, going through everyone who
+    /// has played and then the default names
+    pub fn cycle(&mut self, delta: i32) {
+        let mut names: Vec<String> = self.profiles.iter().map(|p| p.name.clone()).collect();
+        for name in DEFAULT_NAMES {
+            if !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+        if !names.iter().any(|n| n == self.current_name()) {
+            names.push(self.current_name().to_string());
+        }
+        let idx = names
+            .iter()
+            .position(|n| n == self.current_name())
+            .unwrap_or(0);
+        self.current = names[options::cycle(idx, delta, names.len())].clone();
+    }
+}
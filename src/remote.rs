@@ -0,0 +1,286 @@
+use std::fs;
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+
+use crate::game::{Game, GameStatus};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+
+    fn game() -> Game {
+        let mut game = Game::new(1920, 1280, "res".to_string());
+        game.new_level(1);
+        game
+    }
+
+    #[test]
+    fn test_commands() {
+        let mut game = game();
+        assert!(execute(&mut game, "get game_status") == Reply::Now("SplashScreen".to_string()));
+        assert!(execute(&mut game, "confirm") == Reply::Now("ok".to_string()));
+        assert!(game.game_status() == GameStatus::Playing);
+        execute(&mut game, "steer 9");
+        assert!(game.get_pod_delta() == 4);
+        execute(&mut game, "drop");
+        assert!(execute(&mut game, "get pod_status") == Reply::Now("Dropping".to_string()));
+        assert!(execute(&mut game, "pause") == Reply::Now("ok".to_string()));
+        assert!(
+            execute(&mut game, "state")
+                == Reply::Now(
+                    "game_status=Paused pod_status=Dropping level=1 pods=1 men=2 score=0"
+                        .to_string()
+                )
+        );
+        assert!(
+            matches!(execute(&mut game, "get colour"), Reply::Now(e) if e.starts_with("error"))
+        );
+        assert!(matches!(execute(&mut game, "jump"), Reply::Now(e) if e.starts_with("error")));
+        assert!(
+            matches!(execute(&mut game, "steer left"), Reply::Now(e) if e.starts_with("error"))
+        );
+        assert!(
+            execute(&mut game, "wait level 2 30")
+                == Reply::Wait(Wait {
+                    name: "level".to_string(),
+                    value: "2".to_string(),
+                    frames_left: 30,
+                })
+        );
+    }
+
+    #[test]
+    fn test_socket() {
+        let path = std::env::temp_dir().join(format!("xtarda2-remote-{}.sock", std::process::id()));
+        let mut remote = Remote::bind(&path).unwrap();
+        let mut game = game();
+        let mut client = UnixStream::connect(&path).unwrap();
+        client
+            .write_all(b"confirm\ndrop\nwait pod_status Exploding 2\nwait game_status Playing\n")
+            .unwrap();
+        let mut replies = BufReader::new(client.try_clone().unwrap()).lines();
+        for _ in 0..5 {
+            remote.poll(&mut game);
+            game.next_frame();
+        }
+        assert!(replies.next().unwrap().unwrap() == "ok");
+        assert!(replies.next().unwrap().unwrap() == "ok");
+        // The pod is still on its way down
+        assert!(replies.next().unwrap().unwrap() == "timeout");
+        assert!(replies.next().unwrap().unwrap() == "ok");
+        drop(remote);
+        fs::remove_file(&path).unwrap();
+    }
+}
+
+// How long a wait goes on for if it isn't given a time, in frames
+const WAIT_FRAMES: u32 = 600;
+
+const USAGE: &str = "error: commands are drop, launch, steer N, pause, confirm, \
+    get NAME, state and wait NAME VALUE [FRAMES]";
+
+/// Waiting for something in the game to have a certain value
+#[derive(Debug, PartialEq)]
+pub struct Wait {
+    name: String,
+    value: String,
+    frames_left: u32,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Reply {
+    Now(String),
+    /// Replied to once the wait is over
+    Wait(Wait),
+}
+
+/// A value that can be asked for or waited for, as text
+pub fn get(game: &Game, name: &str) -> Option<String> {
+    Some(match name {
+        "game_status" => format!("{:?}", game.game_status()),
+        "pod_status" => format!("{:?}", game.pod_status()),
+        "level" => game.level().to_string(),
+        "pods" | "pods_remaining" => game.pods_remaining().to_string(),
+        "men" | "men_to_rescue" => game.men_to_rescue().to_string(),
+        "score" => game.score().to_string(),
+        _ => return None,
+    })
+}
+
+/// Runs one command
+pub fn execute(game: &mut Game, line: &str) -> Reply {
+    let ok = || Reply::Now("ok".to_string());
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.as_slice() {
+        ["drop"] => {
+            game.drop_pod();
+            ok()
+        }
+        ["launch"] => {
+            game.launch_pod();
+            ok()
+        }
+        ["steer", delta] => match delta.parse::<i32>() {
+            Ok(delta) => {
+                game.pod_set_delta(delta.clamp(-4, 4) as i8);
+                ok()
+            }
+            Err(_) => Reply::Now(USAGE.to_string()),
+        },
+        ["pause"] => {
+            game.pause();
+            ok()
+        }
+        // What Enter does, or Y at the restart prompt
+        ["confirm"] => {
+            if game.game_status() == GameStatus::GameOver {
+                game.restart();
+            } else {
+                game.start();
+            }
+            ok()
+        }
+        ["get", name] => Reply::Now(get(game, name).unwrap_or_else(|| USAGE.to_string())),
+        ["state"] => Reply::Now(
+            ["game_status", "pod_status", "level", "pods", "men", "score"]
+                .iter()
+                .map(|name| format!("{}={}", name, get(game, name).unwrap_or_default()))
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+        ["wait", name, value, frames @ ..] if get(game, name).is_some() => {
+            let frames_left = match frames {
+                [] => Some(WAIT_FRAMES),
+                [frames] => frames.parse().ok(),
+                _ => None,
+            };
+            match frames_left {
+                Some(frames_left) => Reply::Wait(Wait {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                    frames_left,
+                }),
+                None => Reply::Now(USAGE.to_string()),
+            }
+        }
+        _ => Reply::Now(USAGE.to_string()),
+    }
+}
+
+struct Client {
+    stream: UnixStream,
+    // What has been read but not yet run
+    pending: String,
+    wait: Option<Wait>,
+    // Whether more might still be sent
+    open: bool,
+}
+
+impl Client {
+    // Reads whatever has arrived
+    fn read(&mut self) {
+        let mut buffer = [0; 1024];
+        while self.open {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => self.open = false,
+                Ok(n) => self.pending += &String::from_utf8_lossy(&buffer[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => self.open = false,
+            }
+        }
+    }
+
+    // Whether there's anything left to do for this client
+    fn finished(&self) -> bool {
+        !self.open && self.wait.is_none() && !self.pending.contains('\n')
+    }
+
+    fn reply(&mut self, reply: &str) -> bool {
+        writeln!(self.stream, "{}", reply).is_ok()
+    }
+
+    // Runs commands until one has to wait, returning false if the client
+    // can't be replied to
+    fn run(&mut self, game: &mut Game) -> bool {
+        if let Some(wait) = self.wait.as_mut() {
+            if get(game, &wait.name).as_deref() == Some(wait.value.as_str()) {
+                self.wait = None;
+                if !self.reply("ok") {
+                    return false;
+                }
+            } else if wait.frames_left == 0 {
+                self.wait = None;
+                if !self.reply("timeout") {
+                    return false;
+                }
+            } else {
+                wait.frames_left -= 1;
+                return true;
+            }
+        }
+        while let Some(end) = self.pending.find('\n') {
+            let line: String = self.pending.drain(..=end).collect();
+            if line.trim().is_empty() {
+                continue;
+            }
+            match execute(game, &line) {
+                Reply::Now(reply) => {
+                    if !self.reply(&reply) {
+                        return false;
+                    }
+                }
+                Reply::Wait(wait) => {
+                    self.wait = Some(wait);
+                    return true;
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Lets other programs play the game through a Unix domain socket, a line
+/// of text at a time, for testing it from scripts
+pub struct Remote {
+    listener: UnixListener,
+    clients: Vec<Client>,
+}
+
+impl Remote {
+    /// Listens at `path`, replacing any socket left there from before
+    pub fn bind(path: &Path) -> Result<Remote, String> {
+        if fs::metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+            fs::remove_file(path).map_err(|e| e.to_string())?;
+        }
+        let listener = UnixListener::bind(path).map_err(|e| e.to_string())?;
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        Ok(Remote {
+            listener,
+            clients: Vec::new(),
+        })
+    }
+
+    /// Takes in new connections and runs what they've sent. Call it once a
+    /// frame, before the pod is moved.
+    pub fn poll(&mut self, game: &mut Game) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_ok() {
+                self.clients.push(Client {
+                    stream,
+                    pending: String::new(),
+                    wait: None,
+                    open: true,
+                });
+            }
+        }
+        // Clients that have stopped sending still get their replies
+        self.clients.retain_mut(|client| {
+            client.read();
+            client.run(game) && !client.finished()
+        });
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::game::{Game, PodStatus};
+use crate::level::LevelDefinition;
+use crate::mode::GameMode;
+use crate::remote::{self, Reply};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game() -> Game {
+        Game::new(1920, 1280, "res".to_string())
+    }
+
+    #[test]
+    fn test_scenarios() {
+        let mut failures = Vec::new();
+        let mut paths: Vec<_> = fs::read_dir("tests/scenarios")
+            .unwrap()
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        paths.sort();
+        assert!(!paths.is_empty());
+        for path in paths {
+            if let Err(e) = Scenario::load(&path).and_then(|s| s.run(&mut game())) {
+                failures.push(format!("{}: {}", path.display(), e));
+            }
+        }
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn test_failures_are_reported() {
+        let scenario = Scenario::from_toml(
+            r#"
+            [[expect]]
+            tick = 2
+            pod_status = "Dropping"
+            "#,
+        )
+        .unwrap();
+        assert!(
+            scenario.run(&mut game()).unwrap_err()
+                == "tick 2: pod_status is Inactive, not Dropping"
+        );
+        let scenario = Scenario::from_toml("[[input]]\ntick = 0\ndo = \"wait level 2\"").unwrap();
+        assert!(scenario
+            .run(&mut game())
+            .unwrap_err()
+            .contains("can't wait"));
+        let scenario = Scenario::from_toml("[[expect]]\ntick = 0\ncolour = 1").unwrap();
+        assert!(scenario
+            .run(&mut game())
+            .unwrap_err()
+            .contains("nothing called colour"));
+        assert!(Scenario::from_toml("seed = 1\nspeed = 2").is_err());
+    }
+}
+
+/// Where things are at the start of a scenario, when not where the level
+/// puts them
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Setup {
+    pub pod_x: Option<f32>,
+    pub pod_y: Option<f32>,
+    pub pod_status: Option<PodStatus>,
+    pub mothership_x: Option<f32>,
+    pub pods_remaining: Option<u32>,
+    pub men_to_rescue: Option<u32>,
+}
+
+/// A command to run before a frame, like those for `--remote`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Input {
+    tick: u32,
+    #[serde(rename = "do")]
+    command: String,
+}
+
+/// What should be true once a number of frames have gone by, as values
+/// that can be asked for with `get` over `--remote`
+#[derive(Deserialize)]
+struct Expectation {
+    tick: u32,
+    #[serde(flatten)]
+    values: BTreeMap<String, toml::Value>,
+}
+
+/// A scripted test, read from a TOML file: how the game starts, what is
+/// pressed when, and what should have happened by when. See
+/// `tests/scenarios` for examples.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    #[serde(default)]
+    mode: GameMode,
+    #[serde(default = "first_level")]
+    level: u32,
+    /// Made-up levels come from this
+    seed: Option<u64>,
+    /// Played in place of the level's own asteroids, pad and rules
+    layout: Option<LevelDefinition>,
+    #[serde(default)]
+    setup: Setup,
+    #[serde(default, rename = "input")]
+    inputs: Vec<Input>,
+    #[serde(default, rename = "expect")]
+    expectations: Vec<Expectation>,
+}
+
+fn first_level() -> u32 {
+    1
+}
+
+// A value from a scenario file as `remote::get` would put it
+fn as_text(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+impl Scenario {
+    pub fn from_toml(contents: &str) -> Result<Scenario, String> {
+        toml::from_str(contents).map_err(|e| e.to_string())
+    }
+
+    pub fn load(path: &Path) -> Result<Scenario, String> {
+        Scenario::from_toml(&fs::read_to_string(path).map_err(|e| e.to_string())?)
+    }
+
+    /// Plays the scenario through, stopping at the first thing that isn't
+    /// as expected
+    pub fn run(&self, game: &mut Game) -> Result<(), String> {
+        game.set_mode(self.mode);
+        if let Some(seed) = self.seed {
+            game.set_seed(seed);
+        }
+        game.play_level(self.level, self.layout.as_ref());
+        game.set_up(&self.setup);
+        let last = self
+            .inputs
+            .iter()
+            .map(|i| i.tick)
+            .chain(self.expectations.iter().map(|e| e.tick))
+            .max()
+            .unwrap_or(0);
+        for tick in 0..=last {
+            for expectation in self.expectations.iter().filter(|e| e.tick == tick) {
+                for (name, expected) in &expectation.values {
+                    let Some(actual) = remote::get(game, name) else {
+                        return Err(format!("tick {}: there's nothing called {}", tick, name));
+                    };
+                    let expected = as_text(expected);
+                    if actual != expected {
+                        return Err(format!(
+                            "tick {}: {} is {}, not {}",
+                            tick, name, actual, expected
+                        ));
+                    }
+                }
+            }
+            for input in self.inputs.iter().filter(|i| i.tick == tick) {
+                match remote::execute(game, &input.command) {
+                    Reply::Now(reply) if reply.starts_with("error") => {
+                        return Err(format!("tick {}: {}", tick, reply));
+                    }
+                    Reply::Now(_) => {}
+                    Reply::Wait(_) => {
+                        return Err(format!("tick {}: scenarios can't wait", tick));
+                    }
+                }
+            }
+            if game.get_pod_delta() != 0 {
+                game.pod_manoeuvre();
+            }
+            game.next_frame();
+            game.sounds_to_play.clear();
+            game.status_changes.clear();
+            game.events.clear();
+        }
+        Ok(())
+    }
+}
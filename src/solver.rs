@@ -0,0 +1,528 @@
+use std::ops::Range;
+
+use crate::game::{move_asteroid, move_mothership, Game};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(rocks: Vec<Rock>) -> Field {
+        Field {
+            width: 1920.0,
+            size_multiplier: 1.0,
+            rocks,
+            mothership_x: 50.0,
+            mothership_y: 100.0,
+            mothership_direction: 10,
+            mothership_width: 80.0,
+            pod_size: 20.0,
+            landing_pad_x: 835.0,
+            landing_pad_width: 250.0,
+            landing_y: 1200.0,
+            moonbase_x: 1440.0,
+            autodock: true,
+            hit_scale: 1.0,
+        }
+    }
+
+    fn wall(y: f32, speed: f32) -> Vec<Rock> {
+        (-2..21)
+            .map(|n| Rock {
+                x: n as f32 * 100.0,
+                y,
+                speed,
+                blobs: [(0.0, 0.0, 60.0); 3],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_spread_and_ranges() {
+        let grid = Grid {
+            cells: 130,
+            cell_width: 4.0,
+        };
+        let mut cells = grid.empty();
+        Grid::set_range(&mut cells, 62, 65);
+        assert!((60..68).filter(|c| Grid::get(&cells, *c)).eq(62..66));
+        let spread = grid.spread(&cells);
+        assert!((0..130).filter(|c| Grid::get(&spread, *c)).eq(61..67));
+        let mut edge = grid.empty();
+        Grid::set(&mut edge, 129);
+        assert!(grid.spread(&edge)[2] == 0b11 && grid.spread(&edge)[3] == 0);
+        assert!(grid.cell(-1.0).is_none() && grid.cell(6.0) == Some(1));
+    }
+
+    #[test]
+    fn test_empty_field() {
+        let report = check(&field(Vec::new()), 20.0);
+        // It takes a while to steer over to the pad and then drop down to it
+        let descent = report.descent_frames.unwrap();
+        assert!(descent > 200 && descent < 400);
+        assert!(report.round_trip_frames.unwrap() > descent);
+        assert!(report.is_solvable());
+    }
+
+    #[test]
+    fn test_wall_blocks_descent() {
+        let report = check(&field(wall(600.0, 0.5)), 20.0);
+        assert!(report.descent_frames.is_none());
+        assert!(!report.is_solvable());
+    }
+
+    #[test]
+    fn test_gap_in_wall() {
+        let mut rocks = wall(600.0, 0.5);
+        // Take out a few next to each other to leave a moving gap
+        rocks.drain(8..11);
+        assert!(check(&field(rocks), 20.0).is_solvable());
+    }
+
+    #[test]
+    fn test_not_enough_time() {
+        assert!(!check(&field(Vec::new()), 2.0).is_solvable());
+    }
+
+    #[test]
+    fn test_tolerance_opens_gaps() {
+        let mut field = field(wall(600.0, 0.5));
+        field.hit_scale = 0.5;
+        assert!(check(&field, 20.0).is_solvable());
+    }
+
+    #[test]
+    fn test_without_autodock_the_mothership_must_be_met() {
+        let mut field = field(Vec::new());
+        field.autodock = false;
+        assert!(check(&field, 20.0).is_solvable());
+        // Never comes back within reach
+        field.mothership_direction = 0;
+        field.mothership_x = 1800.0;
+        field.landing_pad_x = 0.0;
+        field.moonbase_x = 0.0;
+        assert!(!check(&field, 5.0).is_solvable());
+    }
+
+    #[test]
+    fn test_looking_ahead() {
+        let field = field(wall(600.0, 0.0));
+        // Far enough above the wall not to see it yet
+        assert!(can_survive(&field, (960.0, 300.0), false, (0.0, 0), 40));
+        assert!(!can_survive(&field, (960.0, 300.0), false, (0.0, 0), 80));
+        // Off the side of the screen
+        assert!(!can_survive(&field, (-50.0, 300.0), false, (0.0, 1), 10));
+        let mut rocks = wall(600.0, 0.0);
+        rocks.drain(8..11);
+        let field = self::field(rocks);
+        assert!(can_survive(&field, (900.0, 300.0), false, (0.0, 0), 200));
+        // Going up from below the wall
+        assert!(can_survive(&field, (900.0, 1000.0), true, (0.0, 0), 300));
+    }
+
+    #[test]
+    fn test_generated_levels() {
+        let report = survey(&mut Game::new(1920, 1280, "res".to_string()), 0..4, 2, 20.0);
+        assert!(report.contains("Level 1"));
+        assert!(report.contains("Level 2"));
+    }
+}
+
+const FRAMES_PER_SECOND: f32 = 60.0;
+// The pod moves 5 pixels a frame up or down and up to 4 across
+const POD_SPEED: f32 = 5.0;
+const POD_MAX_DELTA: f32 = 4.0;
+// Pixels per frame the rescued man walks to the pod
+const MAN_SPEED: f32 = 10.0;
+
+/// An asteroid: where it starts, how fast it moves and its three blobs as
+/// (x offset, y offset, radius), all in window pixels
+#[derive(Clone)]
+pub struct Rock {
+    pub x: f32,
+    pub y: f32,
+    pub speed: f32,
+    pub blobs: [(f32, f32, f32); 3],
+}
+
+/// A snapshot of everything on screen that matters for getting a pod down
+/// to the landing pad and back
+#[derive(Clone)]
+pub struct Field {
+    pub width: f32,
+    pub size_multiplier: f32,
+    pub rocks: Vec<Rock>,
+    pub mothership_x: f32,
+    pub mothership_y: f32,
+    pub mothership_direction: i8,
+    pub mothership_width: f32,
+    pub pod_size: f32,
+    pub landing_pad_x: f32,
+    pub landing_pad_width: f32,
+    /// Pod position once it has landed
+    pub landing_y: f32,
+    pub moonbase_x: f32,
+    pub autodock: bool,
+    /// How much of each blob's radius the pod has to touch to crash
+    pub hit_scale: f32,
+}
+
+/// When the quickest safe paths finish, in frames from the start of the level
+pub struct Report {
+    pub descent_frames: Option<u32>,
+    pub round_trip_frames: Option<u32>,
+}
+
+impl Report {
+    pub fn is_solvable(&self) -> bool {
+        self.round_trip_frames.is_some()
+    }
+}
+
+// Sets of pod positions across the screen, one bit per cell. A cell is as
+// wide as the pod's biggest move in one frame, so each frame a pod can get
+// to the cells either side of the one it is in.
+// The screen is always 480 cells wide, as the pod's moves scale with it.
+type Cells = [u64; WORDS];
+const WORDS: usize = 8;
+
+struct Grid {
+    cells: usize,
+    cell_width: f32,
+}
+
+impl Grid {
+    fn empty(&self) -> Cells {
+        [0; WORDS]
+    }
+
+    fn cell(&self, x: f32) -> Option<usize> {
+        let cell = (x / self.cell_width).floor();
+        (cell >= 0.0 && (cell as usize) < self.cells).then_some(cell as usize)
+    }
+
+    // Left edge of the pod in a cell
+    fn x(&self, cell: usize) -> f32 {
+        cell as f32 * self.cell_width
+    }
+
+    fn set(cells: &mut Cells, cell: usize) {
+        cells[cell / 64] |= 1 << (cell % 64);
+    }
+
+    // Sets cells first to last inclusive, a word at a time
+    fn set_range(cells: &mut Cells, first: usize, last: usize) {
+        let words = cells.iter_mut().enumerate();
+        for (word, bits) in words.take(last / 64 + 1).skip(first / 64) {
+            let from = if word == first / 64 { first % 64 } else { 0 };
+            let to = if word == last / 64 { last % 64 } else { 63 };
+            *bits |= (u64::MAX >> (63 - to)) & (u64::MAX << from);
+        }
+    }
+
+    fn get(cells: &Cells, cell: usize) -> bool {
+        cells[cell / 64] & (1 << (cell % 64)) != 0
+    }
+
+    /// Every cell whose pod position is within the range
+    fn range(&self, from: f32, to: f32) -> Cells {
+        let mut cells = self.empty();
+        for cell in 0..self.cells {
+            if (from..=to).contains(&self.x(cell)) {
+                Grid::set(&mut cells, cell);
+            }
+        }
+        cells
+    }
+
+    /// Where the pods could be after one frame of steering
+    fn spread(&self, cells: &Cells) -> Cells {
+        std::array::from_fn(|word| {
+            let mut spread = cells[word] | cells[word] << 1 | cells[word] >> 1;
+            if word > 0 {
+                spread |= cells[word - 1] >> 63;
+            }
+            if word + 1 < WORDS {
+                spread |= cells[word + 1] << 63;
+            }
+            // Nothing off the right of the screen
+            match self.cells.saturating_sub(word * 64) {
+                0 => 0,
+                bits if bits < 64 => spread & ((1 << bits) - 1),
+                _ => spread,
+            }
+        })
+    }
+}
+
+fn and(a: &Cells, b: &Cells) -> Cells {
+    std::array::from_fn(|word| a[word] & b[word])
+}
+
+fn and_not(a: &Cells, b: &Cells) -> Cells {
+    std::array::from_fn(|word| a[word] & !b[word])
+}
+
+fn is_empty(cells: &Cells) -> bool {
+    cells.iter().all(|word| *word == 0)
+}
+
+// Heights the pod passes through, evenly spaced
+struct Rows {
+    first: f32,
+    step: f32,
+    count: usize,
+}
+
+impl Rows {
+    fn y(&self, row: usize) -> f32 {
+        self.first + self.step * row as f32
+    }
+
+    // Rows from y to y + height
+    fn between(&self, y: f32, height: f32) -> Range<usize> {
+        let (a, b) = (
+            (y - self.first) / self.step,
+            (y + height - self.first) / self.step,
+        );
+        let from = a.min(b).ceil().max(0.0) as usize;
+        let to = (a.max(b).floor() + 1.0).clamp(0.0, self.count as f32) as usize;
+        from..to.max(from)
+    }
+}
+
+// Cells a pod can't be in at each height without hitting an asteroid
+fn blocked(field: &Field, grid: &Grid, rows: &Rows) -> Vec<Cells> {
+    let mut blocked = vec![grid.empty(); rows.count];
+    let half_pod = field.pod_size / 2.0;
+    for rock in &field.rocks {
+        for (dx, dy, radius) in rock.blobs {
+            let centre_x = rock.x + dx + radius;
+            let centre_y = rock.y + dy + radius;
+            let radius = radius * field.hit_scale;
+            for row in rows.between(centre_y - radius - half_pod, 2.0 * radius) {
+                let height = rows.y(row) + half_pod - centre_y;
+                if height.abs() > radius {
+                    continue;
+                }
+                let half_chord = (radius * radius - height * height).sqrt();
+                let from = centre_x - half_chord - half_pod;
+                let to = centre_x + half_chord - half_pod;
+                let first = (from / grid.cell_width).ceil().max(0.0) as usize;
+                let last = (to / grid.cell_width).floor();
+                if last < 0.0 {
+                    continue;
+                }
+                let last = (last as usize).min(grid.cells - 1);
+                if first <= last {
+                    Grid::set_range(&mut blocked[row], first, last);
+                }
+            }
+        }
+    }
+    blocked
+}
+
+/// Looks for a way to drop a pod onto the landing pad and fly it back up
+/// again without touching an asteroid, within the given number of seconds.
+/// Every frame the asteroids move on and the pod can be launched, steered
+/// one cell left or right, or left where it is, so the search follows the
+/// set of every position a pod could have reached at each height.
+pub fn check(field: &Field, seconds: f32) -> Report {
+    let m = field.size_multiplier;
+    let grid = Grid {
+        cells: ((field.width / (POD_MAX_DELTA * m)).ceil() as usize).min(WORDS * 64),
+        cell_width: POD_MAX_DELTA * m,
+    };
+    let step = POD_SPEED * m;
+    // Heights the pod passes through on the way down and back up
+    let start_y = field.mothership_y + 30.0;
+    let down = Rows {
+        first: start_y,
+        step,
+        count: ((field.landing_y - start_y) / step).ceil().max(1.0) as usize,
+    };
+    let up = Rows {
+        first: field.landing_y,
+        step: -step,
+        count: ((field.landing_y - field.mothership_y - 10.0) / step)
+            .ceil()
+            .max(1.0) as usize,
+    };
+    let pad = grid.range(
+        field.landing_pad_x - field.pod_size / 2.0,
+        field.landing_pad_x + field.landing_pad_width - field.pod_size / 2.0,
+    );
+
+    let mut field = field.clone();
+    let mut descending = vec![grid.empty(); down.count];
+    let mut ascending = vec![grid.empty(); up.count];
+    // For each cell on the pad, when a pod there could have its passenger aboard
+    let mut waiting: Vec<Option<u32>> = vec![None; grid.cells];
+    let mut report = Report {
+        descent_frames: None,
+        round_trip_frames: None,
+    };
+    for frame in 0..(seconds * FRAMES_PER_SECOND) as u32 {
+        (field.mothership_x, field.mothership_direction) = move_mothership(
+            field.mothership_x,
+            field.mothership_direction,
+            field.width,
+            field.mothership_width,
+            m,
+        );
+        for rock in &mut field.rocks {
+            rock.x = move_asteroid(rock.x, rock.speed, field.width, m);
+        }
+        let blocked_down = blocked(&field, &grid, &down);
+        let blocked_up = blocked(&field, &grid, &up);
+
+        for row in (0..down.count).rev() {
+            if is_empty(&descending[row]) {
+                continue;
+            }
+            let pods = grid.spread(&std::mem::take(&mut descending[row]));
+            let pods = and_not(&pods, &blocked_down[row]);
+            if row + 1 < down.count {
+                descending[row + 1] = pods;
+                continue;
+            }
+            let landed = and(&pods, &pad);
+            for cell in (0..grid.cells).filter(|cell| Grid::get(&landed, *cell)) {
+                let walk = ((field.moonbase_x - grid.x(cell)).abs() / (MAN_SPEED * m)) as u32;
+                waiting[cell].get_or_insert(frame + walk);
+                report.descent_frames.get_or_insert(frame);
+            }
+        }
+        if let Some(cell) = grid.cell(field.mothership_x + 25.0 + field.mothership_direction as f32)
+        {
+            Grid::set(&mut descending[0], cell);
+        }
+
+        for row in (0..up.count).rev() {
+            if is_empty(&ascending[row]) {
+                continue;
+            }
+            let pods = grid.spread(&std::mem::take(&mut ascending[row]));
+            let pods = and_not(&pods, &blocked_up[row]);
+            if row + 1 < up.count {
+                ascending[row + 1] = pods;
+                continue;
+            }
+            let docked = if field.autodock {
+                pods
+            } else {
+                and(
+                    &pods,
+                    &grid.range(
+                        field.mothership_x,
+                        field.mothership_x + field.mothership_width - field.pod_size,
+                    ),
+                )
+            };
+            if !is_empty(&docked) {
+                report.round_trip_frames = Some(frame);
+                return report;
+            }
+        }
+        // A pod can sit on the pad for as long as it likes once loaded
+        for (cell, ready) in waiting.iter().enumerate() {
+            if ready.is_some_and(|ready| ready <= frame) {
+                Grid::set(&mut ascending[0], cell);
+            }
+        }
+    }
+    report
+}
+
+/// Whether a pod at (x, y) can keep clear of the asteroids for the next
+/// `frames` frames, or until it gets to the bottom or top if that's sooner.
+/// It moves `dx` across each frame for the first `held` frames and can then
+/// be steered any way. This is what a pilot who can only see so far ahead
+/// has to go on.
+pub fn can_survive(
+    field: &Field,
+    (x, y): (f32, f32),
+    rising: bool,
+    (dx, held): (f32, u32),
+    frames: u32,
+) -> bool {
+    let m = field.size_multiplier;
+    let grid = Grid {
+        cells: ((field.width / (POD_MAX_DELTA * m)).ceil() as usize).min(WORDS * 64),
+        cell_width: POD_MAX_DELTA * m,
+    };
+    let (step, end_y) = if rising {
+        (-POD_SPEED * m, field.mothership_y + 10.0)
+    } else {
+        (POD_SPEED * m, field.landing_y)
+    };
+    let rows = Rows {
+        first: y,
+        step,
+        count: (((end_y - y) / step).ceil().max(1.0) as usize).min(frames as usize),
+    };
+    let mut field = field.clone();
+    let mut pods = grid.empty();
+    if let Some(cell) = grid.cell(x) {
+        Grid::set(&mut pods, cell);
+    }
+    let mut x = x;
+    for row in 0..rows.count {
+        for rock in &mut field.rocks {
+            rock.x = move_asteroid(rock.x, rock.speed, field.width, m);
+        }
+        if row < held as usize {
+            x += dx;
+            pods = grid.empty();
+            if let Some(cell) = grid.cell(x) {
+                Grid::set(&mut pods, cell);
+            }
+        } else {
+            pods = grid.spread(&pods);
+        }
+        let row_rows = Rows {
+            first: rows.y(row),
+            step,
+            count: 1,
+        };
+        pods = and_not(&pods, &blocked(&field, &grid, &row_rows)[0]);
+        if is_empty(&pods) {
+            return false;
+        }
+    }
+    true
+}
+
+/// How often generated levels can be finished, for each level over a range
+/// of seeds, and how many rerolls the generator needs to find one that can
+pub fn survey(game: &mut Game, seeds: Range<u64>, levels: u32, seconds: f32) -> String {
+    let mut lines = vec![format!(
+        "Seeds {}..{}, {} seconds to get down and back",
+        seeds.start, seeds.end, seconds
+    )];
+    for level in 1..=levels {
+        let mut solvable = 0;
+        let mut rerolls = 0;
+        let mut unsolved = 0;
+        for seed in seeds.clone() {
+            game.set_seed(seed);
+            let attempts = game.generate_asteroid_field(level, seconds);
+            if attempts.first() == Some(&true) {
+                solvable += 1;
+            }
+            match attempts.iter().position(|ok| *ok) {
+                Some(attempt) => rerolls += attempt,
+                None => unsolved += 1,
+            }
+        }
+        let count = seeds.end.saturating_sub(seeds.start).max(1);
+        lines.push(format!(
+            "Level {}: {:.1}% solvable first time, {:.2} rerolls on average, {} never solved",
+            level,
+            100.0 * solvable as f32 / count as f32,
+            rerolls as f32 / count as f32,
+            unsolved
+        ));
+    }
+    lines.join("\n")
+}
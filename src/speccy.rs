@@ -0,0 +1,272 @@
+use sfml::cpp::FBox;
+use sfml::graphics::{
+    Color, FloatRect, RenderTarget, RenderTexture, RenderWindow, Sprite, Texture, Transformable,
+    View,
+};
+use sfml::SfResult;
+
+use crate::game::Game;
+use crate::theme::SPECTRUM_PALETTE;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(width: usize, height: usize, colour: Color) -> Vec<u8> {
+        let mut pixels = Vec::new();
+        for _ in 0..width * height {
+            pixels.extend_from_slice(&[colour.r, colour.g, colour.b, 255]);
+        }
+        pixels
+    }
+
+    fn set_pixel(pixels: &mut [u8], width: usize, x: usize, y: usize, colour: Color) {
+        let offset = (y * width + x) * 4;
+        pixels[offset..offset + 3].copy_from_slice(&[colour.r, colour.g, colour.b]);
+    }
+
+    fn pixel(pixels: &[u8], width: usize, x: usize, y: usize) -> Color {
+        let offset = (y * width + x) * 4;
+        Color::rgb(pixels[offset], pixels[offset + 1], pixels[offset + 2])
+    }
+
+    #[test]
+    fn test_nearest_colour() {
+        assert!(nearest_colour(0, 0, 0) == 0);
+        assert!(nearest_colour(0, 250, 10) == 11);
+        assert!(nearest_colour(0, 200, 0) == 4);
+        assert!(nearest_colour(30, 30, 30) == 0);
+    }
+
+    #[test]
+    fn test_attribute_picks_two_most_common() {
+        let mut counts = [0; 15];
+        counts[0] = 40;
+        counts[4] = 20;
+        counts[2] = 4;
+        assert!(attribute(&counts) == Attribute { ink: 4, paper: 0 });
+    }
+
+    #[test]
+    fn test_attribute_single_colour() {
+        let mut counts = [0; 15];
+        counts[5] = 64;
+        assert!(attribute(&counts) == Attribute { ink: 5, paper: 5 });
+    }
+
+    #[test]
+    fn test_attribute_shares_bright() {
+        let mut counts = [0; 15];
+        // bright green paper with a few normal red pixels: ink becomes bright red
+        counts[11] = 50;
+        counts[2] = 14;
+        assert!(attribute(&counts) == Attribute { ink: 9, paper: 11 });
+    }
+
+    #[test]
+    fn test_quantize_clash() {
+        // One 8x8 cell: mostly black, some bright green and a single bright red pixel
+        let green = SPECTRUM_PALETTE[11];
+        let red = SPECTRUM_PALETTE[9];
+        let mut pixels = buffer(8, 8, Color::BLACK);
+        for x in 0..8 {
+            set_pixel(&mut pixels, 8, x, 0, green);
+        }
+        set_pixel(&mut pixels, 8, 3, 4, red);
+        quantize(&mut pixels, 8, 8);
+        assert!(pixel(&pixels, 8, 0, 0) == green);
+        assert!(pixel(&pixels, 8, 0, 1) == Color::BLACK);
+        // Only two colours allowed per cell, so the red pixel clashes
+        assert!(pixel(&pixels, 8, 3, 4) != red);
+    }
+
+    #[test]
+    fn test_quantize_cells_are_independent() {
+        let blue = SPECTRUM_PALETTE[1];
+        let yellow = SPECTRUM_PALETTE[6];
+        let mut pixels = buffer(16, 8, blue);
+        for y in 0..8 {
+            for x in 8..16 {
+                set_pixel(&mut pixels, 16, x, y, yellow);
+            }
+        }
+        quantize(&mut pixels, 16, 8);
+        assert!(pixel(&pixels, 16, 7, 7) == blue);
+        assert!(pixel(&pixels, 16, 8, 0) == yellow);
+    }
+
+    #[test]
+    fn test_border_colour() {
+        assert!(border_colour(Color::rgb(0, 150, 0)) == SPECTRUM_PALETTE[4]);
+        assert!(border_colour(Color::rgb(255, 255, 0)) == SPECTRUM_PALETTE[6]);
+    }
+}
+
+/// Size of the Spectrum's display file in pixels
+pub const WIDTH: u32 = 256;
+pub const HEIGHT: u32 = 192;
+/// Border width around the display, in Spectrum pixels
+const BORDER_X: u32 = 32;
+const BORDER_Y: u32 = 24;
+/// Attribute cells are 8x8 pixels with one ink and one paper colour each
+const CELL: usize = 8;
+
+/// The ink and paper colours of one attribute cell, as SPECTRUM_PALETTE indices
+#[derive(Debug, Eq, PartialEq)]
+pub struct Attribute {
+    pub ink: usize,
+    pub paper: usize,
+}
+
+fn is_bright(idx: usize) -> bool {
+    idx >= 8
+}
+
+// Palette index of the same colour with the given BRIGHT setting.
+// Black is the same either way.
+fn with_bright(idx: usize, bright: bool) -> usize {
+    match (idx, is_bright(idx), bright) {
+        (0, _, _) => 0,
+        (_, false, true) => idx + 7,
+        (_, true, false) => idx - 7,
+        _ => idx,
+    }
+}
+
+fn colour_distance(colour: Color, r: u8, g: u8, b: u8) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    d(colour.r, r) + d(colour.g, g) + d(colour.b, b)
+}
+
+/// Index of the palette colour closest to the given colour
+pub fn nearest_colour(r: u8, g: u8, b: u8) -> usize {
+    (0..SPECTRUM_PALETTE.len())
+        .min_by_key(|&idx| colour_distance(SPECTRUM_PALETTE[idx], r, g, b))
+        .unwrap_or(0)
+}
+
+/// Chooses a cell's attribute from how many of its pixels are closest to each
+/// palette colour. The most common colour becomes the paper and the next
+/// most common the ink. As on the real hardware both share one BRIGHT bit,
+/// which follows the paper.
+pub fn attribute(counts: &[u32; 15]) -> Attribute {
+    let mut order: Vec<usize> = (0..counts.len()).collect();
+    // stable sort keeps lower palette indices first when counts are tied
+    order.sort_by(|a, b| counts[*b].cmp(&counts[*a]));
+    let paper = order[0];
+    let ink = if counts[order[1]] > 0 {
+        order[1]
+    } else {
+        paper
+    };
+    let bright = if paper == 0 {
+        is_bright(ink)
+    } else {
+        is_bright(paper)
+    };
+    Attribute {
+        ink: with_bright(ink, bright),
+        paper: with_bright(paper, bright),
+    }
+}
+
+/// Reduces an RGBA pixel buffer to Spectrum colours, allowing only two colours
+/// in each 8x8 cell so that colour clash appears as it did on real hardware
+pub fn quantize(pixels: &mut [u8], width: usize, height: usize) {
+    for cell_y in (0..height).step_by(CELL) {
+        for cell_x in (0..width).step_by(CELL) {
+            let offsets: Vec<usize> = (cell_y..(cell_y + CELL).min(height))
+                .flat_map(|y| {
+                    (cell_x..(cell_x + CELL).min(width)).map(move |x| (y * width + x) * 4)
+                })
+                .collect();
+            let mut counts = [0; 15];
+            for &offset in &offsets {
+                let p = &pixels[offset..offset + 3];
+                counts[nearest_colour(p[0], p[1], p[2])] += 1;
+            }
+            let attr = attribute(&counts);
+            let ink = SPECTRUM_PALETTE[attr.ink];
+            let paper = SPECTRUM_PALETTE[attr.paper];
+            for &offset in &offsets {
+                let p = &mut pixels[offset..offset + 4];
+                let colour = if colour_distance(ink, p[0], p[1], p[2])
+                    < colour_distance(paper, p[0], p[1], p[2])
+                {
+                    ink
+                } else {
+                    paper
+                };
+                p.copy_from_slice(&[colour.r, colour.g, colour.b, 255]);
+            }
+        }
+    }
+}
+
+/// The border could only show the eight normal (non-bright) colours
+pub fn border_colour(colour: Color) -> Color {
+    SPECTRUM_PALETTE[with_bright(nearest_colour(colour.r, colour.g, colour.b), false)]
+}
+
+/// Draws the game at the Spectrum's resolution, applies attribute clash on
+/// the CPU and then scales the result up to the window with a border.
+///
+/// SFML draws the frame into a small texture, which is read back into
+/// `pixels`. Drawing it on the CPU instead would need a software version of
+/// every sprite, shape and bit of text `Game::draw_screen` uses, so only the
+/// quantizing is done there.
+pub struct SpeccyDisplay {
+    target: FBox<RenderTexture>,
+    screen: FBox<Texture>,
+    pixels: Vec<u8>,
+}
+
+impl SpeccyDisplay {
+    pub fn new(window_width: u32, window_height: u32) -> SfResult<SpeccyDisplay> {
+        let mut target = RenderTexture::new(WIDTH, HEIGHT)?;
+        // The game draws in window coordinates; squash them into the display
+        let view = View::from_rect(FloatRect::new(
+            0.0,
+            0.0,
+            window_width as f32,
+            window_height as f32,
+        ))?;
+        target.set_view(&view);
+        let mut screen = Texture::new()?;
+        screen.create(WIDTH, HEIGHT)?;
+        screen.set_smooth(false);
+        Ok(SpeccyDisplay {
+            target,
+            screen,
+            pixels: Vec::new(),
+        })
+    }
+
+    pub fn draw(&mut self, game: &mut Game, window: &mut RenderWindow) {
+        self.target.clear(Color::BLACK);
+        game.draw_screen(&mut *self.target);
+        self.target.display();
+        let Ok(image) = self.target.texture().copy_to_image() else {
+            return;
+        };
+        self.pixels.clear();
+        self.pixels.extend_from_slice(image.pixel_data());
+        quantize(&mut self.pixels, WIDTH as usize, HEIGHT as usize);
+        self.screen
+            .update_from_pixels(&self.pixels, WIDTH, HEIGHT, 0, 0);
+
+        window.clear(border_colour(game.theme().pad));
+        let view_size = window.view().size();
+        let scale = f32::min(
+            view_size.x / (WIDTH + 2 * BORDER_X) as f32,
+            view_size.y / (HEIGHT + 2 * BORDER_Y) as f32,
+        );
+        let mut sprite = Sprite::with_texture(&self.screen);
+        sprite.set_scale((scale, scale));
+        sprite.set_position((
+            (view_size.x - WIDTH as f32 * scale) / 2.0,
+            (view_size.y - HEIGHT as f32 * scale) / 2.0,
+        ));
+        window.draw(&sprite);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::game::{GameStatus, ManStatus, PodStatus};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transitions() {
+        use GameStatus::*;
+        assert!(Transition::Start.from(SplashScreen) == Some(Playing));
+        assert!(Transition::Pause.from(Playing) == Some(Paused));
+        assert!(Transition::Resume.from(Paused) == Some(Playing));
+        // Enter doesn't start a game from the pause screen, and there's
+        // nothing to pause on the title screen
+        assert!(Transition::Start.from(Paused).is_none());
+        assert!(Transition::Pause.from(SplashScreen).is_none());
+        assert!(Transition::GameOver.from(Options).is_none());
+        // The title screen can always be got back to
+        for status in [
+            Playing,
+            GameOver,
+            NewLevel,
+            Paused,
+            Options,
+            Stats,
+            Achievements,
+            Editor,
+            Attract,
+        ] {
+            assert!(Transition::BackToTitle.from(status) == Some(SplashScreen));
+        }
+    }
+
+    #[test]
+    fn test_pod_changes() {
+        use PodStatus::*;
+        assert!(pod_can_change(Inactive, Dropping));
+        // Another pod can go while the last one is still going up in flames
+        assert!(pod_can_change(Exploding, Dropping));
+        assert!(pod_can_change(Ascending, AutoDock));
+        assert!(!pod_can_change(Inactive, Ascending));
+        assert!(!pod_can_change(ReadyForTakeOff, Exploding));
+        assert!(!pod_can_change(AutoDock, Dropping));
+    }
+
+    #[test]
+    fn test_man_changes() {
+        use ManStatus::*;
+        assert!(man_can_change(Inactive, EnteringPod));
+        assert!(man_can_change(EnteringPod, Inactive));
+        assert!(!man_can_change(Inactive, Inactive));
+        assert!(!man_can_change(EnteringPod, EnteringPod));
+    }
+}
+
+/// Something that moves the game from one screen to another
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Transition {
+    /// To the level select screen, to choose where to start
+    ChooseLevel,
+    /// Into a new game, from the title screen, level select or editor
+    Start,
+    /// Straight back to the start of the practice level
+    RestartLevel,
+    Pause,
+    Resume,
+    /// To the screen between levels
+    NextLevel,
+    /// On from the screen between levels
+    Continue,
+    GameOver,
+    /// Away from wherever the game is, back to the title screen
+    BackToTitle,
+    OpenOptions,
+    OpenStats,
+    OpenAchievements,
+    OpenEditor,
+    /// Attract mode's bot starts playing
+    PlayDemo,
+    /// Attract mode moves on to the high scores or controls
+    ShowAttract,
+}
+
+impl Transition {
+    /// Where the transition goes from `status`, or None if it can't happen
+    /// there
+    pub fn from(&self, status: GameStatus) -> Option<GameStatus> {
+        use GameStatus::*;
+        let to = match (self, status) {
+            (Transition::ChooseLevel, SplashScreen) => LevelSelect,
+            (Transition::Start, SplashScreen | LevelSelect | Editor) => Playing,
+            (Transition::RestartLevel, Playing) => Playing,
+            (Transition::Pause, Playing) => Paused,
+            (Transition::Resume, Paused) => Playing,
+            (Transition::NextLevel, Playing) => NewLevel,
+            (Transition::Continue, NewLevel) => Playing,
+            (Transition::GameOver, Playing) => GameOver,
+            (Transition::BackToTitle, _) => SplashScreen,
+            (Transition::OpenOptions, SplashScreen) => Options,
+            (Transition::OpenStats, SplashScreen) => Stats,
+            (Transition::OpenAchievements, SplashScreen) => Achievements,
+            (Transition::OpenEditor, SplashScreen) => Editor,
+            (Transition::PlayDemo, SplashScreen) => Playing,
+            (Transition::ShowAttract, Playing | Paused | NewLevel | GameOver | Attract) => Attract,
+            _ => return None,
+        };
+        Some(to)
+    }
+}
+
+/// A transition that has happened, for the front end to react to
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StatusChange {
+    pub transition: Transition,
+    pub from: GameStatus,
+    pub to: GameStatus,
+}
+
+/// Whether a pod can go straight from one status to another. A new game or
+/// level puts the pod away whatever it was doing, so doesn't go through here.
+pub fn pod_can_change(from: PodStatus, to: PodStatus) -> bool {
+    use PodStatus::*;
+    matches!(
+        (from, to),
+        (Inactive | Exploding, Dropping)
+            | (Dropping, ReadyForTakeOff | Exploding)
+            | (ReadyForTakeOff, Ascending)
+            | (Ascending, AutoDock | Exploding | Inactive)
+            | (AutoDock, Inactive)
+            | (Exploding, Inactive)
+    )
+}
+
+/// Whether the terran can go straight from one status to another: out of the
+/// moonbase once the pod has landed, and into the pod
+pub fn man_can_change(from: ManStatus, to: ManStatus) -> bool {
+    use ManStatus::*;
+    matches!(
+        (from, to),
+        (Inactive, EnteringPod) | (EnteringPod, Inactive)
+    )
+}
@@ -0,0 +1,368 @@
+use std::cell::RefCell;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::events::{Cause, Event, EventKind, Subscriber};
+use crate::profile::{Profile, Profiles};
+use crate::timeattack::FRAMES_PER_SECOND;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hear(stats: &mut Stats, tick: u32, kind: EventKind) {
+        stats.notify(&Event {
+            tick,
+            x: 0.0,
+            y: 0.0,
+            kind,
+        });
+    }
+
+    #[test]
+    fn test_adding_up() {
+        let mut stats = Stats::default();
+        hear(
+            &mut stats,
+            0,
+            EventKind::GameStarted {
+                level: 2,
+                on_record: true,
+            },
+        );
+        hear(&mut stats, 10, EventKind::PodDropped);
+        hear(&mut stats, 20, EventKind::NearMiss { closeness: 0.5 });
+        hear(&mut stats, 30, EventKind::Landed);
+        hear(&mut stats, 90, EventKind::Docked { autodock: false });
+        hear(&mut stats, 100, EventKind::PodDropped);
+        // A near miss doesn't count if the pod doesn't get away with it
+        hear(&mut stats, 110, EventKind::NearMiss { closeness: 0.9 });
+        hear(
+            &mut stats,
+            120,
+            EventKind::PodDestroyed {
+                cause: Cause::Asteroid,
+                ascending: false,
+                from_mothership: 0.5,
+            },
+        );
+        hear(&mut stats, 130, EventKind::GameOver { level: 3, score: 0 });
+        // The next game's time starts from nothing
+        hear(
+            &mut stats,
+            0,
+            EventKind::GameStarted {
+                level: 1,
+                on_record: true,
+            },
+        );
+        hear(&mut stats, 50, EventKind::PodDropped);
+        // Quitting counts the time up to then, and nothing after
+        hear(&mut stats, 70, EventKind::GameQuit { level: 1 });
+        hear(&mut stats, 90, EventKind::PodDropped);
+        // Practice and the like aren't counted at all
+        hear(
+            &mut stats,
+            0,
+            EventKind::GameStarted {
+                level: 20,
+                on_record: false,
+            },
+        );
+        hear(&mut stats, 500, EventKind::PodDropped);
+        assert!(stats.games_played == 2);
+        assert!(stats.terrans_rescued() == 1 && stats.manual_dockings == 1);
+        assert!(stats.pods_lost() == 1 && stats.lost_to_asteroids == 1);
+        assert!(stats.lost_going_down == 1 && stats.lost_going_up == 0);
+        assert!(stats.near_misses_survived == 1);
+        assert!(stats.highest_level == 3);
+        assert!(stats.play_frames == 200);
+        assert!(stats.accuracy() == 1.0 / 3.0);
+    }
+
+    #[test]
+    fn test_exports() {
+        let mut profile = Profile::new("Smith, J");
+        profile.stats.pods_dropped = 4;
+        profile.stats.autodockings = 1;
+        let csv = to_csv(&[profile.clone()]);
+        let mut lines = csv.lines();
+        assert!(lines.next().unwrap().starts_with("player,games_played,"));
+        assert!(lines.next().unwrap().starts_with("\"Smith, J\",0,4,1,0,1,"));
+        let json = to_json(&[profile]).unwrap();
+        assert!(json.contains("\"player\": \"Smith, J\""));
+        assert!(json.contains("\"accuracy\": 0.25"));
+        assert!(export(&[], Path::new("stats.txt")).is_err());
+    }
+}
+
+/// A player's statistics over every game on their record, added up from the
+/// game's events
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Stats {
+    pub games_played: u32,
+    pub pods_dropped: u32,
+    pub manual_dockings: u32,
+    pub autodockings: u32,
+    pub lost_to_asteroids: u32,
+    pub lost_to_ground: u32,
+    pub lost_to_mothership: u32,
+    pub lost_going_down: u32,
+    pub lost_going_up: u32,
+    pub near_misses_survived: u32,
+    pub highest_level: u32,
+    /// Time spent playing, in frames
+    pub play_frames: u64,
+    // Near misses since the pod was dropped or landed, which only count once
+    // it gets where it's going
+    #[serde(skip)]
+    near_misses: u32,
+    // When the last event happened, for adding up the time played
+    #[serde(skip)]
+    last_tick: u32,
+    // Whether the game being played goes on the player's record
+    #[serde(skip)]
+    counting: bool,
+}
+
+impl Stats {
+    pub fn terrans_rescued(&self) -> u32 {
+        self.manual_dockings + self.autodockings
+    }
+
+    pub fn pods_lost(&self) -> u32 {
+        self.lost_to_asteroids + self.lost_to_ground + self.lost_to_mothership
+    }
+
+    /// The share of pods dropped that came back with a terran, from 0.0 to
+    /// 1.0
+    pub fn accuracy(&self) -> f32 {
+        if self.pods_dropped == 0 {
+            return 0.0;
+        }
+        self.terrans_rescued() as f32 / self.pods_dropped as f32
+    }
+
+    /// Labels and values for the stats screen
+    pub fn lines(&self) -> Vec<(&'static str, String)> {
+        let seconds = self.play_frames / FRAMES_PER_SECOND as u64;
+        vec![
+            ("Games played", self.games_played.to_string()),
+            ("Terrans rescued", self.terrans_rescued().to_string()),
+            ("Pods dropped", self.pods_dropped.to_string()),
+            ("Accuracy", format!("{:.0}%", self.accuracy() * 100.0)),
+            ("Docked by hand", self.manual_dockings.to_string()),
+            ("Autodocked", self.autodockings.to_string()),
+            (
+                "Near misses survived",
+                self.near_misses_survived.to_string(),
+            ),
+            (
+                "Pods lost",
+                format!(
+                    "{} ({} going down, {} going up)",
+                    self.pods_lost(),
+                    self.lost_going_down,
+                    self.lost_going_up
+                ),
+            ),
+            ("  to asteroids", self.lost_to_asteroids.to_string()),
+            ("  to the ground", self.lost_to_ground.to_string()),
+            ("  to the mothership", self.lost_to_mothership.to_string()),
+            ("Highest level", self.highest_level.to_string()),
+            (
+                "Time played",
+                format!("{}h {:02}m", seconds / 3600, seconds / 60 % 60),
+            ),
+        ]
+    }
+}
+
+impl Subscriber for Stats {
+    fn notify(&mut self, event: &Event) {
+        // Each game's ticks start again from nothing
+        if let EventKind::GameStarted { on_record, .. } = event.kind {
+            self.last_tick = event.tick;
+            self.counting = on_record;
+        }
+        if !self.counting {
+            return;
+        }
+        self.play_frames += event.tick.saturating_sub(self.last_tick) as u64;
+        self.last_tick = event.tick;
+        match event.kind {
+            EventKind::GameStarted { level, .. } => {
+                self.games_played += 1;
+                self.highest_level = self.highest_level.max(level);
+            }
+            EventKind::PodDropped => {
+                self.pods_dropped += 1;
+                self.near_misses = 0;
+            }
+            EventKind::NearMiss { .. } => self.near_misses += 1,
+            EventKind::Landed => {
+                self.near_misses_survived += self.near_misses;
+                self.near_misses = 0;
+            }
+            EventKind::Docked { autodock } => {
+                if autodock {
+                    self.autodockings += 1;
+                } else {
+                    self.manual_dockings += 1;
+                }
+                self.near_misses_survived += self.near_misses;
+                self.near_misses = 0;
+            }
+            EventKind::PodDestroyed {
+                cause, ascending, ..
+            } => {
+                match cause {
+                    Cause::Asteroid => self.lost_to_asteroids += 1,
+                    Cause::Ground => self.lost_to_ground += 1,
+                    Cause::Mothership => self.lost_to_mothership += 1,
+                }
+                if ascending {
+                    self.lost_going_up += 1;
+                } else {
+                    self.lost_going_down += 1;
+                }
+                self.near_misses = 0;
+            }
+            EventKind::LevelComplete { level, .. } | EventKind::GameOver { level, .. } => {
+                self.highest_level = self.highest_level.max(level);
+            }
+            // Nothing more is counted until the next game starts
+            EventKind::GameQuit { .. } => self.counting = false,
+            EventKind::RescueeBoarded
+            | EventKind::Launched
+            | EventKind::BonusAwarded { .. }
+            | EventKind::AchievementUnlocked { .. } => {}
+        }
+    }
+}
+
+/// Adds the game's events up into the current player's statistics, saving
+/// them at the end of each level and game, and when the player quits
+pub struct StatsRecorder {
+    profiles: Rc<RefCell<Profiles>>,
+}
+
+impl StatsRecorder {
+    pub fn new(profiles: Rc<RefCell<Profiles>>) -> StatsRecorder {
+        StatsRecorder { profiles }
+    }
+}
+
+impl Subscriber for StatsRecorder {
+    fn notify(&mut self, event: &Event) {
+        let mut profiles = self.profiles.borrow_mut();
+        profiles.current_mut().stats.notify(event);
+        if let EventKind::LevelComplete { .. }
+        | EventKind::GameOver { .. }
+        | EventKind::GameQuit { .. } = event.kind
+        {
+            if let Err(e) = profiles.save() {
+                eprintln!("Couldn't save profiles: {}", e);
+            }
+        }
+    }
+}
+
+/// One player's statistics as they're exported
+#[derive(Serialize)]
+struct Row<'a> {
+    player: &'a str,
+    games_played: u32,
+    pods_dropped: u32,
+    terrans_rescued: u32,
+    manual_dockings: u32,
+    autodockings: u32,
+    accuracy: f32,
+    near_misses_survived: u32,
+    pods_lost: u32,
+    lost_to_asteroids: u32,
+    lost_to_ground: u32,
+    lost_to_mothership: u32,
+    lost_going_down: u32,
+    lost_going_up: u32,
+    highest_level: u32,
+    play_seconds: u64,
+}
+
+// The columns of `Row`, in order
+const CSV_HEADER: &str = "player,games_played,pods_dropped,terrans_rescued,manual_dockings,\
+    autodockings,accuracy,near_misses_survived,pods_lost,lost_to_asteroids,lost_to_ground,\
+    lost_to_mothership,lost_going_down,lost_going_up,highest_level,play_seconds";
+
+fn rows(profiles: &[Profile]) -> Vec<Row<'_>> {
+    profiles
+        .iter()
+        .map(|profile| {
+            let stats = &profile.stats;
+            Row {
+                player: &profile.name,
+                games_played: stats.games_played,
+                pods_dropped: stats.pods_dropped,
+                terrans_rescued: stats.terrans_rescued(),
+                manual_dockings: stats.manual_dockings,
+                autodockings: stats.autodockings,
+                accuracy: stats.accuracy(),
+                near_misses_survived: stats.near_misses_survived,
+                pods_lost: stats.pods_lost(),
+                lost_to_asteroids: stats.lost_to_asteroids,
+                lost_to_ground: stats.lost_to_ground,
+                lost_to_mothership: stats.lost_to_mothership,
+                lost_going_down: stats.lost_going_down,
+                lost_going_up: stats.lost_going_up,
+                highest_level: stats.highest_level,
+                play_seconds: stats.play_frames / FRAMES_PER_SECOND as u64,
+            }
+        })
+        .collect()
+}
+
+/// Everyone's statistics as CSV, a line each under a header
+pub fn to_csv(profiles: &[Profile]) -> String {
+    let mut csv = CSV_HEADER.to_string() + "\n";
+    for row in rows(profiles) {
+        csv += &format!(
+            "\"{}\",{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+            row.player.replace('"', "\"\""),
+            row.games_played,
+            row.pods_dropped,
+            row.terrans_rescued,
+            row.manual_dockings,
+            row.autodockings,
+            row.accuracy,
+            row.near_misses_survived,
+            row.pods_lost,
+            row.lost_to_asteroids,
+            row.lost_to_ground,
+            row.lost_to_mothership,
+            row.lost_going_down,
+            row.lost_going_up,
+            row.highest_level,
+            row.play_seconds
+        );
+    }
+    csv
+}
+
+pub fn to_json(profiles: &[Profile]) -> Result<String, String> {
+    serde_json::to_string_pretty(&rows(profiles)).map_err(|e| e.to_string())
+}
+
+/// Writes everyone's statistics to `path`, as CSV or JSON going by its
+/// extension
+pub fn export(profiles: &[Profile], path: &Path) -> Result<(), String> {
+    let contents = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => to_csv(profiles),
+        Some("json") => to_json(profiles)?,
+        _ => return Err("the file should end in .csv or .json".to_string()),
+    };
+    fs::write(path, contents).map_err(|e| e.to_string())
+}
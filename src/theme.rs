@@ -0,0 +1,281 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+use sfml::graphics::Color;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_colour() {
+        assert!(parse_colour("#ffb000") == Ok(Color::rgb(255, 176, 0)));
+        assert!(parse_colour("  #00FF00 ") == Ok(Color::rgb(0, 255, 0)));
+        assert!(parse_colour("ffb000").is_err());
+        assert!(parse_colour("#ffb0").is_err());
+        assert!(parse_colour("#gg0000").is_err());
+    }
+
+    #[test]
+    fn test_theme_from_toml() {
+        let theme = Theme::from_toml(
+            "name = \"Test\"\n\
+             pod = \"#ff0000\"\n\
+             asteroid_2 = \"#0000ff\"\n",
+        )
+        .unwrap();
+        let classic = Theme::classic_green();
+        assert!(theme.name == "Test");
+        assert!(theme.pod == Color::rgb(255, 0, 0));
+        assert!(theme.asteroid[1] == Color::rgb(0, 0, 255));
+        // Roles not given in the file fall back to the classic theme
+        assert!(theme.mothership == classic.mothership);
+        assert!(theme.asteroid[0] == classic.asteroid[0]);
+    }
+
+    #[test]
+    fn test_theme_from_toml_errors() {
+        assert!(Theme::from_toml("pod = \"#ff0000\"").is_err());
+        assert!(Theme::from_toml("name = \"Bad\"\npod = \"red\"").is_err());
+    }
+
+    #[test]
+    fn test_scaled() {
+        let classic = Theme::classic_green();
+        assert!(classic.star(100) == Color::rgb(0, 100, 0));
+        assert!(scaled(Color::rgb(255, 176, 0), 128) == Color::rgb(128, 88, 0));
+    }
+}
+
+/// The full ZX Spectrum palette: black followed by the seven normal and
+/// seven bright colours (bright black is the same as black so is omitted).
+pub const SPECTRUM_PALETTE: [Color; 15] = [
+    Color::rgb(0, 0, 0),
+    Color::rgb(0, 0, 215),
+    Color::rgb(215, 0, 0),
+    Color::rgb(215, 0, 215),
+    Color::rgb(0, 215, 0),
+    Color::rgb(0, 215, 215),
+    Color::rgb(215, 215, 0),
+    Color::rgb(215, 215, 215),
+    Color::rgb(0, 0, 255),
+    Color::rgb(255, 0, 0),
+    Color::rgb(255, 0, 255),
+    Color::rgb(0, 255, 0),
+    Color::rgb(0, 255, 255),
+    Color::rgb(255, 255, 0),
+    Color::rgb(255, 255, 255),
+];
+
+/// The colour used for each thing drawn on screen
+#[derive(Clone)]
+pub struct Theme {
+    pub name: String,
+    pub mothership: Color,
+    pub pod: Color,
+    pub explosion: Color,
+    pub man: Color,
+    /// Asteroid blobs, front (layer 1) to back (layer 3)
+    pub asteroid: [Color; 3],
+    pub ground: Color,
+    pub hills: Color,
+    pub pad: Color,
+    pub moonbase: Color,
+    /// Brightest star colour, dimmed per star by its luminosity
+    pub stars: Color,
+    /// Titles and the status bar
+    pub text: Color,
+    /// "Press ENTER" style prompts
+    pub prompt: Color,
+    /// Secondary messages
+    pub message: Color,
+}
+
+// Theme files are TOML with a name and an optional "#rrggbb" string per role
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    name: String,
+    mothership: Option<String>,
+    pod: Option<String>,
+    explosion: Option<String>,
+    man: Option<String>,
+    asteroid_1: Option<String>,
+    asteroid_2: Option<String>,
+    asteroid_3: Option<String>,
+    ground: Option<String>,
+    hills: Option<String>,
+    pad: Option<String>,
+    moonbase: Option<String>,
+    stars: Option<String>,
+    text: Option<String>,
+    prompt: Option<String>,
+    message: Option<String>,
+}
+
+/// Scales a colour's brightness, where 255 leaves it unchanged
+pub fn scaled(colour: Color, luminosity: u8) -> Color {
+    let scale = |c: u8| (c as u32 * luminosity as u32 / 255) as u8;
+    Color::rgb(scale(colour.r), scale(colour.g), scale(colour.b))
+}
+
+pub fn parse_colour(s: &str) -> Result<Color, String> {
+    let s = s.trim();
+    let hex = s
+        .strip_prefix('#')
+        .filter(|h| h.len() == 6)
+        .ok_or(format!("'{}' is not a #rrggbb colour", s))?;
+    let component = |i: usize| {
+        u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| format!("'{}' is not valid hex", s))
+    };
+    Ok(Color::rgb(component(0)?, component(2)?, component(4)?))
+}
+
+impl Theme {
+    /// A single-colour theme using the same brightness levels as the
+    /// original green display
+    fn monochrome(name: &str, base: Color) -> Theme {
+        Theme {
+            name: name.to_string(),
+            mothership: base,
+            pod: base,
+            explosion: base,
+            man: base,
+            asteroid: [scaled(base, 120), scaled(base, 100), scaled(base, 80)],
+            ground: scaled(base, 96),
+            hills: scaled(base, 64),
+            pad: scaled(base, 120),
+            moonbase: scaled(base, 110),
+            stars: base,
+            text: scaled(base, 200),
+            prompt: scaled(base, 150),
+            message: scaled(base, 120),
+        }
+    }
+
+    pub fn classic_green() -> Theme {
+        Theme::monochrome("Classic Green", Color::rgb(0, 255, 0))
+    }
+
+    pub fn amber() -> Theme {
+        Theme::monochrome("Amber", Color::rgb(255, 176, 0))
+    }
+
+    pub fn zx_spectrum() -> Theme {
+        let p = &SPECTRUM_PALETTE;
+        Theme {
+            name: "ZX Spectrum".to_string(),
+            mothership: p[12],
+            pod: p[14],
+            explosion: p[13],
+            man: p[13],
+            asteroid: [p[9], p[3], p[1]],
+            ground: p[4],
+            hills: p[5],
+            pad: p[6],
+            moonbase: p[7],
+            stars: p[14],
+            text: p[13],
+            prompt: p[14],
+            message: p[5],
+        }
+    }
+
+    pub fn high_contrast() -> Theme {
+        let white = Color::rgb(255, 255, 255);
+        let yellow = Color::rgb(255, 255, 0);
+        let cyan = Color::rgb(0, 255, 255);
+        Theme {
+            name: "High Contrast".to_string(),
+            mothership: yellow,
+            pod: yellow,
+            explosion: yellow,
+            man: yellow,
+            asteroid: [white, Color::rgb(200, 200, 200), Color::rgb(150, 150, 150)],
+            ground: Color::rgb(0, 0, 255),
+            hills: Color::rgb(0, 0, 160),
+            pad: cyan,
+            moonbase: Color::rgb(255, 0, 255),
+            stars: Color::rgb(128, 128, 128),
+            text: white,
+            prompt: yellow,
+            message: cyan,
+        }
+    }
+
+    pub fn built_in() -> Vec<Theme> {
+        vec![
+            Theme::classic_green(),
+            Theme::amber(),
+            Theme::zx_spectrum(),
+            Theme::high_contrast(),
+        ]
+    }
+
+    /// Parses a theme file. Any role the file leaves out keeps its
+    /// classic green colour.
+    pub fn from_toml(contents: &str) -> Result<Theme, String> {
+        let file: ThemeFile = toml::from_str(contents).map_err(|e| e.to_string())?;
+        let mut theme = Theme::classic_green();
+        theme.name = file.name;
+        let roles = [
+            (&file.mothership, &mut theme.mothership),
+            (&file.pod, &mut theme.pod),
+            (&file.explosion, &mut theme.explosion),
+            (&file.man, &mut theme.man),
+            (&file.ground, &mut theme.ground),
+            (&file.hills, &mut theme.hills),
+            (&file.pad, &mut theme.pad),
+            (&file.moonbase, &mut theme.moonbase),
+            (&file.stars, &mut theme.stars),
+            (&file.text, &mut theme.text),
+            (&file.prompt, &mut theme.prompt),
+            (&file.message, &mut theme.message),
+        ];
+        for (value, colour) in roles {
+            if let Some(value) = value {
+                *colour = parse_colour(value)?;
+            }
+        }
+        let layers = [&file.asteroid_1, &file.asteroid_2, &file.asteroid_3];
+        for (value, colour) in layers.into_iter().zip(theme.asteroid.iter_mut()) {
+            if let Some(value) = value {
+                *colour = parse_colour(value)?;
+            }
+        }
+        Ok(theme)
+    }
+
+    /// Returns the built-in themes followed by any `.toml` files found in
+    /// the given directory. Files that fail to parse are reported and skipped.
+    pub fn load_all(dir: &Path) -> Vec<Theme> {
+        let mut themes = Theme::built_in();
+        let Ok(entries) = fs::read_dir(dir) else {
+            return themes;
+        };
+        let mut paths: Vec<_> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            match fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|contents| Theme::from_toml(&contents))
+            {
+                Ok(theme) => themes.push(theme),
+                Err(e) => eprintln!("Ignoring theme file {}: {}", path.display(), e),
+            }
+        }
+        themes
+    }
+
+    pub fn star(&self, luminosity: u8) -> Color {
+        scaled(self.stars, luminosity)
+    }
+
+    pub fn explosion_flash(&self, luminosity: u8) -> Color {
+        scaled(self.explosion, luminosity)
+    }
+}
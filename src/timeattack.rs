@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_time() {
+        assert!(format_time(0) == "0:00.0");
+        assert!(format_time(90) == "0:01.5");
+        assert!(format_time(60 * 75 + 6) == "1:15.1");
+    }
+
+    #[test]
+    fn test_par_and_limit() {
+        assert!(par_frames(2) == 2 * PAR_SECONDS_PER_MAN * FRAMES_PER_SECOND);
+        assert!(limit_frames(par_frames(2)) == 2 * par_frames(2));
+        let quick = LevelTime {
+            level: 1,
+            frames: par_frames(2) - 1,
+            par: par_frames(2),
+        };
+        assert!(quick.under_par());
+        let slow = LevelTime {
+            frames: par_frames(2),
+            ..quick
+        };
+        assert!(!slow.under_par());
+    }
+}
+
+// The game runs at this many frames a second, so this many simulation ticks
+// make up one second on the clock
+pub const FRAMES_PER_SECOND: u32 = 60;
+// A run is this many levels long
+pub const LEVELS: u32 = 5;
+// Seconds allowed for each man to be rescued to make par
+const PAR_SECONDS_PER_MAN: u32 = 12;
+// Beating par earns these
+pub const BONUS_PODS: u32 = 1;
+pub const BONUS_POINTS: u32 = 250;
+// Every level of a run is made from this seed, so all runs are on the same
+// asteroid fields and their times can be compared
+pub const SEED: u64 = 1982;
+
+/// Par for a level with this many men to rescue, in frames
+pub fn par_frames(men_to_rescue: u32) -> u32 {
+    men_to_rescue * PAR_SECONDS_PER_MAN * FRAMES_PER_SECOND
+}
+
+/// How long there is before the mothership leaves orbit, in frames
+pub fn limit_frames(par: u32) -> u32 {
+    2 * par
+}
+
+/// Frames as minutes, seconds and tenths
+pub fn format_time(frames: u32) -> String {
+    let tenths = frames * 10 / FRAMES_PER_SECOND;
+    format!("{}:{:02}.{}", tenths / 600, tenths / 10 % 60, tenths % 10)
+}
+
+/// How long a level took
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct LevelTime {
+    pub level: u32,
+    pub frames: u32,
+    pub par: u32,
+}
+
+impl LevelTime {
+    pub fn under_par(&self) -> bool {
+        self.frames < self.par
+    }
+}